@@ -0,0 +1,38 @@
+use std::{collections::HashMap, rc::Rc};
+
+use crate::{function::Function, literal::Literal};
+
+/// A class declared with `class Name { ... }`. Calling it makes an
+/// instance.
+pub struct Class {
+    pub name: String,
+    pub superclass: Option<Rc<Class>>,
+    pub methods: HashMap<String, Function>,
+}
+
+impl Class {
+    /// Finds a method on this class or, failing that, its ancestors.
+    pub fn find_method(&self, name: &str) -> Option<&Function> {
+        match self.methods.get(name) {
+            Some(method) => Some(method),
+            None => self.superclass.as_ref()?.find_method(name),
+        }
+    }
+}
+
+pub struct Instance {
+    pub class: Rc<Class>,
+    pub fields: HashMap<String, Literal>,
+    /// Set by `freeze()`; a frozen instance's fields can't be assigned.
+    pub frozen: bool,
+}
+
+impl Instance {
+    pub fn new(class: &Rc<Class>) -> Self {
+        Self {
+            class: Rc::clone(class),
+            fields: HashMap::new(),
+            frozen: false,
+        }
+    }
+}
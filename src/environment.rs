@@ -6,6 +6,12 @@ pub struct Environment {
     enclosing: Option<Rc<RefCell<Environment>>>,
 }
 
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Environment {
     pub fn new() -> Self {
         Self {
@@ -37,10 +43,48 @@ impl Environment {
         }
     }
 
+    /// The bindings made directly in this scope, not its enclosing ones.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Literal)> {
+        self.values
+            .iter()
+            .map(|(name, value)| (name.as_str(), value))
+    }
+
     pub fn define(&mut self, name: impl Into<String>, value: Literal) {
         self.values.insert(name.into(), value);
     }
 
+    /// Looks `name` up exactly `distance` scopes out from this one, where
+    /// the resolver found its binding.
+    pub fn fetch_at(&self, distance: usize, name: &str) -> Option<Literal> {
+        if distance == 0 {
+            return self.values.get(name).cloned();
+        }
+        return self
+            .enclosing
+            .as_ref()?
+            .borrow()
+            .fetch_at(distance - 1, name);
+    }
+
+    /// Assigns to `name` exactly `distance` scopes out from this one.
+    /// Returns false if it isn't bound there.
+    pub fn assign_at(&mut self, distance: usize, name: &str, value: Literal) -> bool {
+        if distance == 0 {
+            return match self.values.get_mut(name) {
+                Some(slot) => {
+                    *slot = value;
+                    true
+                }
+                None => false,
+            };
+        }
+        match &self.enclosing {
+            Some(enclosing) => return enclosing.borrow_mut().assign_at(distance - 1, name, value),
+            None => return false,
+        }
+    }
+
     pub fn fetch(&self, name: impl Into<String>) -> Option<Literal> {
         let name: String = name.into();
         match self.values.get(&name) {
@@ -1,18 +1,105 @@
-use crate::{literal::Literal, token::Token};
+use std::fmt::Display;
+
+use crate::{
+    function::Arity,
+    literal::Literal,
+    resolver::ResolutionError,
+    scanner::ScanError,
+    token::{Token, TokenType},
+};
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum LoxErrorType {
     SyntaxError(String),
     RuntimeError(DetailedErrorType),
     Return(Literal),
+    /// Unwinds to the innermost loop, which stops iterating.
+    Break,
+    /// Unwinds to the innermost loop, which moves on to its next iteration.
+    Continue,
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum DetailedErrorType {
-    ExpectedNumber,
-    UndeclaredIdentifier,
-    InvalidArity,
+    /// Holds the type name of the operand that wasn't a number.
+    ExpectedNumber(&'static str),
+    UndeclaredIdentifier(String),
+    /// The counts a function accepts, and the count it was called with.
+    InvalidArity(Arity, usize),
     NotCallable,
+    ReturnOutsideFunction,
+    UnknownKeywordArgument,
+    DuplicateArgument,
+    InvalidArgument(String),
+    NegativeShiftAmount,
+    OnlyInstancesHaveProperties,
+    OnlyInstancesHaveFields,
+    StringTooLong,
+    UnhashableKey,
+    CapabilityDisabled(&'static str),
+    Io(String),
+    AssertionFailed(Option<String>),
+    UndefinedProperty(String),
+    FrozenInstance,
+    SuperclassMustBeAClass,
+    DivisionByZero,
+    /// The type names of the left and right operands.
+    OperandsMustBeAddable(&'static str, &'static str),
+    OperandsMustBeTwoArrays,
+    OperandsMustBeTwoMaps,
+    InvalidRepetitionCount,
+    NanKey,
+}
+
+impl Display for DetailedErrorType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ExpectedNumber(actual) => {
+                write!(f, "Operand must be a number, got {}.", actual)
+            }
+            Self::UndeclaredIdentifier(name) => write!(f, "Undefined variable '{}'.", name),
+            Self::InvalidArity(Arity::Exactly(count), got) => {
+                write!(f, "Expected {} arguments but got {}.", count, got)
+            }
+            Self::InvalidArity(Arity::AtLeast(count), got) => {
+                write!(f, "Expected at least {} arguments but got {}.", count, got)
+            }
+            Self::InvalidArity(Arity::Between(min, max), got) => {
+                write!(f, "Expected {} to {} arguments but got {}.", min, max, got)
+            }
+            Self::NotCallable => write!(f, "Can only call functions and classes."),
+            Self::ReturnOutsideFunction => write!(f, "Can't return from top-level code."),
+            Self::UnknownKeywordArgument => write!(f, "Unknown keyword argument."),
+            Self::DuplicateArgument => write!(f, "Argument passed more than once."),
+            Self::InvalidArgument(msg) => write!(f, "{}", msg),
+            Self::NegativeShiftAmount => write!(f, "Shift amount must not be negative."),
+            Self::OnlyInstancesHaveProperties => write!(f, "Only instances have properties."),
+            Self::OnlyInstancesHaveFields => write!(f, "Only instances have fields."),
+            Self::StringTooLong => write!(f, "String exceeds the maximum length."),
+            Self::UnhashableKey => write!(f, "Map keys must be strings, numbers, booleans or nil."),
+            Self::CapabilityDisabled(capability) => {
+                write!(f, "{} access is disabled.", capability)
+            }
+            Self::Io(msg) => write!(f, "{}", msg),
+            Self::AssertionFailed(None) => write!(f, "Assertion failed."),
+            Self::AssertionFailed(Some(msg)) => write!(f, "Assertion failed: {}", msg),
+            Self::UndefinedProperty(name) => write!(f, "Undefined property '{}'.", name),
+            Self::FrozenInstance => write!(f, "Can't set fields on a frozen instance."),
+            Self::SuperclassMustBeAClass => write!(f, "Superclass must be a class."),
+            Self::DivisionByZero => write!(f, "Division by zero."),
+            Self::OperandsMustBeAddable(left, right) => write!(
+                f,
+                "Operands must be two numbers, strings, arrays or maps, got {} and {}.",
+                left, right
+            ),
+            Self::OperandsMustBeTwoArrays => write!(f, "Operands must both be arrays."),
+            Self::OperandsMustBeTwoMaps => write!(f, "Operands must both be maps."),
+            Self::InvalidRepetitionCount => {
+                write!(f, "A string can only be repeated a whole number of times.")
+            }
+            Self::NanKey => write!(f, "NaN can't be used as a map key."),
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -31,6 +118,13 @@ impl LoxError {
         }
     }
 
+    /// Reports the error at `line` instead of its token's line, for errors
+    /// about an operand that starts somewhere else.
+    pub fn at_line(mut self, line: usize) -> Self {
+        self.line = line;
+        return self;
+    }
+
     pub fn parse_error(token: &Token, msg: impl Into<String>) -> Self {
         Self {
             line: token.line,
@@ -39,3 +133,51 @@ impl LoxError {
         }
     }
 }
+
+impl From<ScanError> for LoxError {
+    fn from(error: ScanError) -> Self {
+        let lexeme = error.lexeme.unwrap_or_default();
+        let token = Token::new(TokenType::EOF, lexeme, error.line, 0);
+        return Self::parse_error(&token, error.message);
+    }
+}
+
+impl From<ResolutionError> for LoxError {
+    fn from(error: ResolutionError) -> Self {
+        return Self::parse_error(error.token(), error.message());
+    }
+}
+
+impl Display for LoxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            LoxErrorType::SyntaxError(msg) => {
+                write!(
+                    f,
+                    "[line {}] Error at '{}': {}",
+                    self.line, self.token.lexeme, msg
+                )
+            }
+            LoxErrorType::RuntimeError(detail) => {
+                write!(f, "{}\n[line {}]", detail, self.line)
+            }
+            // `interpret` turns a stray `return` into a runtime error and the
+            // resolver rejects it up front, so reaching this is a bug.
+            LoxErrorType::Return(_) => {
+                write!(
+                    f,
+                    "Internal error: 'return' escaped its function.\n[line {}]",
+                    self.line
+                )
+            }
+            // The parser only accepts `break` and `continue` inside a loop.
+            LoxErrorType::Break | LoxErrorType::Continue => {
+                write!(
+                    f,
+                    "Internal error: '{}' escaped its loop.\n[line {}]",
+                    self.token.lexeme, self.line
+                )
+            }
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use crate::literal::Literal;
+use crate::stmt::{Parameter, Stmt};
 use crate::token::Token;
 use std::fmt::Debug;
 
@@ -6,24 +7,117 @@ use std::fmt::Debug;
 pub enum Expr {
     Assign(Token, Box<Expr>),
     Binary(Box<Expr>, Token, Box<Expr>),
-    Call(Box<Expr>, Token, Vec<Expr>),
-    Grouping(Box<Expr>),
-    Literal(Literal),
+    Call(Box<Expr>, Token, Vec<Expr>, Vec<(Token, Expr)>),
+    /// An anonymous function: the `fun` keyword, parameters and body.
+    Function(Token, Vec<Parameter>, Vec<Stmt>),
+    Get(Box<Expr>, Token),
+    Grouping(Box<Expr>, usize),
+    /// An interpolated string: its parts, and its opening token, where
+    /// errors about the whole string point.
+    Interpolation(Vec<Expr>, Token),
+    Literal(Literal, usize),
     Logical(Box<Expr>, Token, Box<Expr>),
+    Set(Box<Expr>, Token, Box<Expr>),
+    /// `super.name`: the `super` keyword and the method name.
+    Super(Token, Token),
+    This(Token),
+    /// `condition ? then : else`.
+    Ternary(Box<Expr>, Box<Expr>, Box<Expr>),
     Unary(Token, Box<Expr>),
     Var(Token),
 }
 
+/// One method per kind of expression, called by `Expr::accept` with the
+/// expression's parts. Visitors recurse into subexpressions themselves.
+pub trait ExprVisitor<T> {
+    fn visit_assign_expr(&mut self, name: &Token, value: &Expr) -> T;
+    fn visit_binary_expr(&mut self, left: &Expr, operator: &Token, right: &Expr) -> T;
+    fn visit_call_expr(
+        &mut self,
+        callee: &Expr,
+        paren: &Token,
+        arguments: &[Expr],
+        keywords: &[(Token, Expr)],
+    ) -> T;
+    fn visit_function_expr(&mut self, keyword: &Token, params: &[Parameter], body: &[Stmt]) -> T;
+    fn visit_get_expr(&mut self, object: &Expr, name: &Token) -> T;
+    fn visit_grouping_expr(&mut self, expr: &Expr, line: usize) -> T;
+    fn visit_interpolation_expr(&mut self, parts: &[Expr], start: &Token) -> T;
+    fn visit_literal_expr(&mut self, value: &Literal, line: usize) -> T;
+    fn visit_logical_expr(&mut self, left: &Expr, operator: &Token, right: &Expr) -> T;
+    fn visit_set_expr(&mut self, object: &Expr, name: &Token, value: &Expr) -> T;
+    fn visit_super_expr(&mut self, keyword: &Token, method: &Token) -> T;
+    fn visit_this_expr(&mut self, keyword: &Token) -> T;
+    fn visit_ternary_expr(&mut self, condition: &Expr, then_branch: &Expr, else_branch: &Expr)
+        -> T;
+    fn visit_unary_expr(&mut self, operator: &Token, right: &Expr) -> T;
+    fn visit_var_expr(&mut self, name: &Token) -> T;
+}
+
+impl Expr {
+    pub fn accept<T>(&self, visitor: &mut impl ExprVisitor<T>) -> T {
+        match self {
+            Self::Assign(name, value) => visitor.visit_assign_expr(name, value),
+            Self::Binary(left, operator, right) => visitor.visit_binary_expr(left, operator, right),
+            Self::Call(callee, paren, arguments, keywords) => {
+                visitor.visit_call_expr(callee, paren, arguments, keywords)
+            }
+            Self::Function(keyword, params, body) => {
+                visitor.visit_function_expr(keyword, params, body)
+            }
+            Self::Get(object, name) => visitor.visit_get_expr(object, name),
+            Self::Grouping(expr, line) => visitor.visit_grouping_expr(expr, *line),
+            Self::Interpolation(parts, start) => visitor.visit_interpolation_expr(parts, start),
+            Self::Literal(value, line) => visitor.visit_literal_expr(value, *line),
+            Self::Logical(left, operator, right) => {
+                visitor.visit_logical_expr(left, operator, right)
+            }
+            Self::Set(object, name, value) => visitor.visit_set_expr(object, name, value),
+            Self::Super(keyword, method) => visitor.visit_super_expr(keyword, method),
+            Self::This(keyword) => visitor.visit_this_expr(keyword),
+            Self::Ternary(condition, then_branch, else_branch) => {
+                visitor.visit_ternary_expr(condition, then_branch, else_branch)
+            }
+            Self::Unary(operator, right) => visitor.visit_unary_expr(operator, right),
+            Self::Var(name) => visitor.visit_var_expr(name),
+        }
+    }
+
+    /// The line the expression starts on.
+    pub fn line(&self) -> usize {
+        match self {
+            Self::Binary(left, _, _)
+            | Self::Logical(left, _, _)
+            | Self::Call(left, _, _, _)
+            | Self::Get(left, _)
+            | Self::Set(left, _, _)
+            | Self::Ternary(left, _, _) => left.line(),
+            Self::Grouping(_, line) | Self::Literal(_, line) => *line,
+            Self::Assign(token, _)
+            | Self::Unary(token, _)
+            | Self::Var(token)
+            | Self::This(token)
+            | Self::Super(token, _)
+            | Self::Interpolation(_, token)
+            | Self::Function(token, _, _) => token.line,
+        }
+    }
+}
+
 impl Debug for Expr {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Binary(left, operator, right) => {
                 write!(f, "({} {:?} {:?})", operator.lexeme, left, right)
             }
-            Self::Grouping(expr) => {
+            Self::Grouping(expr, _) => {
                 write!(f, "(group {:?})", expr)
             }
-            Self::Literal(expr) => {
+            Self::Interpolation(parts, _) => {
+                let parts: Vec<_> = parts.iter().map(|part| format!("{:?}", part)).collect();
+                write!(f, "(interpolate {})", parts.join(" "))
+            }
+            Self::Literal(expr, _) => {
                 write!(f, "{}", expr)
             }
             Self::Unary(operator, expr) => {
@@ -38,11 +132,38 @@ impl Debug for Expr {
             Self::Logical(left, operator, right) => {
                 write!(f, "({} {:?} {:?})", operator.lexeme, left, right)
             }
-            Self::Call(callee, _, arguments) => {
-                let args: Vec<_> = arguments.iter().map(|arg| format!("{:?}", arg)).collect();
+            Self::Call(callee, _, arguments, keywords) => {
+                let args = arguments.iter().map(|arg| format!("{:?}", arg));
+                let keywords = keywords
+                    .iter()
+                    .map(|(name, arg)| format!("{}: {:?}", name.lexeme, arg));
+                let args: Vec<_> = args.chain(keywords).collect();
                 let args = args.join(" ");
                 write!(f, "({:?} {:?})", callee, args)
             }
+            Self::Function(_, params, _) => {
+                let params: Vec<_> = params.iter().map(|p| p.name.lexeme.as_str()).collect();
+                write!(f, "(fun ({}))", params.join(" "))
+            }
+            Self::Get(object, name) => {
+                write!(f, "(. {:?} {})", object, name.lexeme)
+            }
+            Self::Set(object, name, value) => {
+                write!(f, "(set {:?} {} {:?})", object, name.lexeme, value)
+            }
+            Self::Super(_, method) => {
+                write!(f, "(super {})", method.lexeme)
+            }
+            Self::This(_) => {
+                write!(f, "this")
+            }
+            Self::Ternary(condition, then_branch, else_branch) => {
+                write!(
+                    f,
+                    "(?: {:?} {:?} {:?})",
+                    condition, then_branch, else_branch
+                )
+            }
         }
     }
 }
@@ -54,7 +175,7 @@ mod tests {
 
     #[test]
     fn test_serialize_grouping() {
-        let expr = Expr::Grouping(Box::new(Expr::Literal(Literal::Number(45.67))));
+        let expr = Expr::Grouping(Box::new(Expr::Literal(Literal::Number(45.67), 1)), 1);
 
         let actual = format!("{:?}", expr);
         assert_eq!("(group 45.67)", actual);
@@ -63,8 +184,8 @@ mod tests {
     #[test]
     fn test_serialize_unary() {
         let expr = Expr::Unary(
-            Token::new(TokenType::Minus, "-".to_string(), 1),
-            Box::new(Expr::Literal(Literal::Number(45.67))),
+            Token::new(TokenType::Minus, "-".to_string(), 1, 0),
+            Box::new(Expr::Literal(Literal::Number(45.67), 1)),
         );
 
         let actual = format!("{:?}", expr);
@@ -74,17 +195,118 @@ mod tests {
     #[test]
     fn test_serialize_binary() {
         let left = Expr::Unary(
-            Token::new(TokenType::Minus, "-".to_string(), 1),
-            Box::new(Expr::Literal(Literal::Number(123.0))),
+            Token::new(TokenType::Minus, "-".to_string(), 1, 0),
+            Box::new(Expr::Literal(Literal::Number(123.0), 1)),
         );
 
-        let right = Expr::Grouping(Box::new(Expr::Literal(Literal::Number(45.67))));
+        let right = Expr::Grouping(Box::new(Expr::Literal(Literal::Number(45.67), 1)), 1);
 
-        let operator = Token::new(TokenType::Star, "*".to_string(), 1);
+        let operator = Token::new(TokenType::Star, "*".to_string(), 1, 0);
 
         let expr = Expr::Binary(Box::new(left), operator, Box::new(right));
 
         let actual = format!("{:?}", expr);
         assert_eq!("(* (- 123) (group 45.67))", actual);
     }
+
+    /// Counts every node in an expression tree.
+    struct NodeCounter;
+
+    impl ExprVisitor<usize> for NodeCounter {
+        fn visit_assign_expr(&mut self, _name: &Token, value: &Expr) -> usize {
+            1 + value.accept(self)
+        }
+
+        fn visit_binary_expr(&mut self, left: &Expr, _operator: &Token, right: &Expr) -> usize {
+            1 + left.accept(self) + right.accept(self)
+        }
+
+        fn visit_call_expr(
+            &mut self,
+            callee: &Expr,
+            _paren: &Token,
+            arguments: &[Expr],
+            keywords: &[(Token, Expr)],
+        ) -> usize {
+            let arguments: usize = arguments.iter().map(|arg| arg.accept(self)).sum();
+            let keywords: usize = keywords.iter().map(|(_, arg)| arg.accept(self)).sum();
+            1 + callee.accept(self) + arguments + keywords
+        }
+
+        fn visit_function_expr(
+            &mut self,
+            _keyword: &Token,
+            _params: &[Parameter],
+            _body: &[Stmt],
+        ) -> usize {
+            1
+        }
+
+        fn visit_get_expr(&mut self, object: &Expr, _name: &Token) -> usize {
+            1 + object.accept(self)
+        }
+
+        fn visit_grouping_expr(&mut self, expr: &Expr, _line: usize) -> usize {
+            1 + expr.accept(self)
+        }
+
+        fn visit_interpolation_expr(&mut self, parts: &[Expr], _start: &Token) -> usize {
+            1 + parts.iter().map(|part| part.accept(self)).sum::<usize>()
+        }
+
+        fn visit_literal_expr(&mut self, _value: &Literal, _line: usize) -> usize {
+            1
+        }
+
+        fn visit_logical_expr(&mut self, left: &Expr, _operator: &Token, right: &Expr) -> usize {
+            1 + left.accept(self) + right.accept(self)
+        }
+
+        fn visit_set_expr(&mut self, object: &Expr, _name: &Token, value: &Expr) -> usize {
+            1 + object.accept(self) + value.accept(self)
+        }
+
+        fn visit_super_expr(&mut self, _keyword: &Token, _method: &Token) -> usize {
+            1
+        }
+
+        fn visit_this_expr(&mut self, _keyword: &Token) -> usize {
+            1
+        }
+
+        fn visit_ternary_expr(
+            &mut self,
+            condition: &Expr,
+            then_branch: &Expr,
+            else_branch: &Expr,
+        ) -> usize {
+            1 + condition.accept(self) + then_branch.accept(self) + else_branch.accept(self)
+        }
+
+        fn visit_unary_expr(&mut self, _operator: &Token, right: &Expr) -> usize {
+            1 + right.accept(self)
+        }
+
+        fn visit_var_expr(&mut self, _name: &Token) -> usize {
+            1
+        }
+    }
+
+    #[test]
+    fn test_visitor_counts_nodes() {
+        let negated = Expr::Unary(
+            Token::new(TokenType::Minus, "-".to_string(), 1, 0),
+            Box::new(Expr::Var(Token::new(
+                TokenType::Identifier("x".to_string()),
+                "x".to_string(),
+                1,
+                1,
+            ))),
+        );
+        let grouped = Expr::Grouping(Box::new(Expr::Literal(Literal::Number(2.0), 1)), 1);
+        let operator = Token::new(TokenType::Star, "*".to_string(), 1, 0);
+        let expr = Expr::Binary(Box::new(negated), operator, Box::new(grouped));
+
+        assert_eq!(5, expr.accept(&mut NodeCounter));
+    }
 }
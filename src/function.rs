@@ -2,56 +2,328 @@ use std::{cell::RefCell, rc::Rc};
 
 use crate::{
     environment::Environment,
-    errors::{LoxError, LoxErrorType},
+    errors::{DetailedErrorType, LoxError, LoxErrorType},
     interpreter::{EvaluationResult, Interpreter},
     literal::Literal,
-    stmt::Stmt,
+    natives::NativeResult,
+    stmt::{Parameter, Stmt},
     token::Token,
 };
 
+/// The Rust side of a native function. Errors are reported at the call's
+/// closing parenthesis.
+pub type NativeFn = dyn Fn(&mut Interpreter, &[Literal]) -> NativeResult;
+
+/// Spreads positional arguments over the named (non-rest) parameters,
+/// leaving unfilled slots empty and returning any surplus separately.
+fn positional_slots(
+    params: &[Parameter],
+    arguments: &[Literal],
+) -> (Vec<Option<Literal>>, Vec<Literal>) {
+    let named = params.iter().filter(|p| !p.rest).count();
+    let mut slots: Vec<_> = arguments.iter().take(named).cloned().map(Some).collect();
+    slots.resize(named, None);
+    let surplus = arguments.get(named..).unwrap_or_default().to_vec();
+    (slots, surplus)
+}
+
+/// How many arguments a native function takes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Arity {
+    Exactly(usize),
+    AtLeast(usize),
+    /// Inclusive at both ends.
+    Between(usize, usize),
+}
+
+impl From<usize> for Arity {
+    fn from(count: usize) -> Self {
+        Self::Exactly(count)
+    }
+}
+
 #[derive(Clone)]
 pub enum Function {
     Native {
-        arity: usize,
-        body: Box<fn(&Vec<Literal>) -> Literal>,
+        arity: Arity,
+        body: Rc<NativeFn>,
     },
     Lox {
-        arity: usize,
-        params: Box<Vec<Token>>,
-        body: Box<Vec<Stmt>>,
+        params: Vec<Parameter>,
+        /// Shared by every copy of the function value, which is what gives
+        /// it an identity.
+        body: Rc<[Stmt]>,
         closure: Rc<RefCell<Environment>>,
     },
+    /// A function with some leading arguments already supplied, as made by
+    /// the `partial` native.
+    Partial {
+        inner: Box<Function>,
+        bound: Vec<Literal>,
+    },
+    /// `outer(inner(x))`, as made by the `compose` native.
+    Composed {
+        outer: Box<Function>,
+        inner: Box<Function>,
+    },
+    /// Takes no arguments and always returns the same value, as made by the
+    /// `constant` native.
+    Constant(Box<Literal>),
+}
+
+/// Functions are equal when they are the same function: copies of one
+/// declaration's value, or the same native. Running a declaration again
+/// makes a new function. Derived functions compare by their parts.
+impl PartialEq for Function {
+    fn eq(&self, rhs: &Self) -> bool {
+        match (self, rhs) {
+            (Self::Native { body: lhs, .. }, Self::Native { body: rhs, .. }) => {
+                Rc::ptr_eq(lhs, rhs)
+            }
+            (
+                Self::Lox {
+                    body: lhs,
+                    closure: lhs_closure,
+                    ..
+                },
+                Self::Lox {
+                    body: rhs,
+                    closure: rhs_closure,
+                    ..
+                },
+            ) => Rc::ptr_eq(lhs, rhs) && Rc::ptr_eq(lhs_closure, rhs_closure),
+            (
+                Self::Partial {
+                    inner: lhs,
+                    bound: lhs_bound,
+                },
+                Self::Partial {
+                    inner: rhs,
+                    bound: rhs_bound,
+                },
+            ) => lhs == rhs && lhs_bound == rhs_bound,
+            (
+                Self::Composed {
+                    outer: lhs_outer,
+                    inner: lhs_inner,
+                },
+                Self::Composed {
+                    outer: rhs_outer,
+                    inner: rhs_inner,
+                },
+            ) => lhs_outer == rhs_outer && lhs_inner == rhs_inner,
+            (Self::Constant(lhs), Self::Constant(rhs)) => lhs == rhs,
+            (_, _) => false,
+        }
+    }
 }
 
 impl Function {
+    /// A copy of a method whose body sees `this` as `instance`.
+    pub fn bind(&self, instance: Literal) -> Function {
+        match self {
+            Self::Lox {
+                params,
+                body,
+                closure,
+            } => {
+                let mut env = Environment::enclose(closure);
+                env.define("this", instance);
+                Self::Lox {
+                    params: params.clone(),
+                    body: Rc::clone(body),
+                    closure: Rc::new(RefCell::new(env)),
+                }
+            }
+            other => other.clone(),
+        }
+    }
+
+    /// The most positional arguments the function takes, not counting the
+    /// surplus a variadic function collects.
     pub fn arity(&self) -> usize {
         match self {
-            Self::Native { arity, .. } => arity.clone(),
-            Self::Lox { arity, .. } => arity.clone(),
+            Self::Native { arity, .. } => match *arity {
+                Arity::Exactly(count) | Arity::AtLeast(count) | Arity::Between(_, count) => count,
+            },
+            Self::Lox { params, .. } => params.iter().filter(|p| !p.rest).count(),
+            Self::Partial { inner, bound } => inner.arity().saturating_sub(bound.len()),
+            Self::Composed { .. } => 1,
+            Self::Constant(_) => 0,
+        }
+    }
+
+    /// The number of arguments that must be passed. Trailing parameters
+    /// with a default value may be omitted at the call site.
+    pub fn required_arity(&self) -> usize {
+        match self {
+            Self::Native { arity, .. } => match *arity {
+                Arity::Exactly(count) | Arity::AtLeast(count) | Arity::Between(count, _) => count,
+            },
+            Self::Lox { params, .. } => params
+                .iter()
+                .filter(|p| p.default.is_none() && !p.rest)
+                .count(),
+            Self::Partial { inner, bound } => inner.required_arity().saturating_sub(bound.len()),
+            Self::Composed { .. } => 1,
+            Self::Constant(_) => 0,
         }
     }
 
+    /// Whether the last parameter collects any surplus arguments, making
+    /// `arity` a minimum rather than a maximum.
+    pub fn is_variadic(&self) -> bool {
+        match self {
+            Self::Native { arity, .. } => matches!(arity, Arity::AtLeast(_)),
+            Self::Composed { .. } | Self::Constant(_) => false,
+            Self::Lox { params, .. } => params.last().is_some_and(|p| p.rest),
+            Self::Partial { inner, .. } => inner.is_variadic(),
+        }
+    }
+
+    /// The argument counts the function accepts.
+    pub fn expected_arity(&self) -> Arity {
+        let required = self.required_arity();
+        if self.is_variadic() {
+            return Arity::AtLeast(required);
+        }
+        if required == self.arity() {
+            return Arity::Exactly(required);
+        }
+        return Arity::Between(required, self.arity());
+    }
+
+    /// Whether the function can be called with `count` positional
+    /// arguments.
+    pub fn accepts(&self, count: usize) -> bool {
+        count >= self.required_arity() && (count <= self.arity() || self.is_variadic())
+    }
+
     pub fn call(
         &self,
         interpreter: &mut Interpreter,
-        arguments: &Vec<Literal>,
+        paren: &Token,
+        arguments: &[Literal],
     ) -> EvaluationResult {
         match self {
-            Self::Native { body, .. } => Ok(body(arguments)),
+            Self::Native { body, .. } => body(interpreter, arguments)
+                .map_err(|detail| LoxError::new(paren, LoxErrorType::RuntimeError(detail))),
+            Self::Lox { params, .. } => {
+                let (slots, surplus) = positional_slots(params, arguments);
+                self.call_with_slots(interpreter, slots, surplus)
+            }
+            Self::Partial { inner, bound } => {
+                inner.call(interpreter, paren, &[bound.as_slice(), arguments].concat())
+            }
+            Self::Composed { outer, inner } => {
+                let value = inner.call(interpreter, paren, arguments)?;
+                outer.call(interpreter, paren, &[value])
+            }
+            Self::Constant(value) => Ok(*value.clone()),
+        }
+    }
+
+    /// Calls the function with a mix of positional and keyword arguments.
+    /// Keywords are matched against parameter names after the positional
+    /// arguments have been assigned, and every parameter without a default
+    /// must end up bound.
+    pub fn call_with_keywords(
+        &self,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        arguments: Vec<Literal>,
+        keywords: Vec<(Token, Literal)>,
+    ) -> EvaluationResult {
+        let params = match self {
+            Self::Lox { params, .. } => params,
+            Self::Partial { inner, bound } => {
+                let arguments = [bound.clone(), arguments].concat();
+                return inner.call_with_keywords(interpreter, paren, arguments, keywords);
+            }
+            Self::Native { .. } | Self::Composed { .. } | Self::Constant(_) => {
+                return Err(LoxError::new(
+                    &keywords[0].0,
+                    LoxErrorType::RuntimeError(DetailedErrorType::UnknownKeywordArgument),
+                ))
+            }
+        };
+
+        let got = arguments.len() + keywords.len();
+        let invalid_arity =
+            LoxErrorType::RuntimeError(DetailedErrorType::InvalidArity(self.expected_arity(), got));
+        let (mut slots, surplus) = positional_slots(params, &arguments);
+        let named = slots.len();
+        if arguments.len() > named && !self.is_variadic() {
+            return Err(LoxError::new(paren, invalid_arity));
+        }
+
+        for (name, value) in keywords {
+            let index = params[..named]
+                .iter()
+                .position(|p| p.name.lexeme == name.lexeme)
+                .ok_or_else(|| {
+                    LoxError::new(
+                        &name,
+                        LoxErrorType::RuntimeError(DetailedErrorType::UnknownKeywordArgument),
+                    )
+                })?;
+            if slots[index].is_some() {
+                return Err(LoxError::new(
+                    &name,
+                    LoxErrorType::RuntimeError(DetailedErrorType::DuplicateArgument),
+                ));
+            }
+            slots[index] = Some(value);
+        }
+
+        let missing = params
+            .iter()
+            .zip(slots.iter())
+            .any(|(param, slot)| slot.is_none() && param.default.is_none());
+        if missing {
+            return Err(LoxError::new(paren, invalid_arity));
+        }
+
+        self.call_with_slots(interpreter, slots, surplus)
+    }
+
+    fn call_with_slots(
+        &self,
+        interpreter: &mut Interpreter,
+        slots: Vec<Option<Literal>>,
+        surplus: Vec<Literal>,
+    ) -> EvaluationResult {
+        match self {
+            Self::Native { .. }
+            | Self::Partial { .. }
+            | Self::Composed { .. }
+            | Self::Constant(_) => unreachable!(),
             Self::Lox {
                 body,
                 params,
                 closure,
                 ..
             } => {
-                let mut env = Environment::enclose(closure);
-                let mut i = 0;
+                let env = Rc::new(RefCell::new(Environment::enclose(closure)));
+                let mut slots = slots.into_iter();
                 for param in params.iter() {
-                    let value = arguments.get(i).unwrap();
-                    env.define(param.lexeme.clone(), value.clone());
-                    i += 1;
+                    if param.rest {
+                        env.borrow_mut()
+                            .define(param.name.lexeme.clone(), Literal::array(surplus));
+                        break;
+                    }
+                    // Defaults are evaluated at call time, in the function's own
+                    // scope, so they can refer to the parameters before them.
+                    let value = match (slots.next().flatten(), &param.default) {
+                        (Some(value), _) => value,
+                        (None, Some(default)) => {
+                            interpreter.evaluate_in(default, Rc::clone(&env))?
+                        }
+                        (None, None) => Literal::Nil,
+                    };
+                    env.borrow_mut().define(param.name.lexeme.clone(), value);
                 }
-                match interpreter.execute_block(body, Rc::new(RefCell::new(env))) {
+                match interpreter.execute_function_body(body, env) {
                     Err(LoxError {
                         kind: LoxErrorType::Return(value),
                         ..
@@ -1,8 +1,14 @@
 use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{self, Write};
 use std::rc::Rc;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::Duration;
 
-use crate::function::Function;
+use crate::class::{Class, Instance};
+use crate::function::{Arity, Function};
+use crate::natives::{self, NativeResult};
+use crate::random::Rng;
+use crate::resolver::{Resolution, ResolutionMap};
 use crate::{
     environment::Environment,
     errors::DetailedErrorType,
@@ -10,27 +16,79 @@ use crate::{
     errors::LoxErrorType,
     expr::Expr,
     literal::Literal,
-    stmt::Stmt,
+    stmt::{Parameter, Stmt},
     token::{Token, TokenType},
 };
 
 pub type EvaluationResult = Result<Literal, LoxError>;
 
+/// Reads an environment variable by name, `None` if it is unset.
+pub type EnvLookup = Box<dyn Fn(&str) -> Option<String>>;
+
+/// Reads the next line of input, `None` once input ends.
+pub type LineReader = Box<dyn FnMut() -> Option<String>>;
+
+/// Library functions written in Lox, on top of the natives.
+const PRELUDE: &str = include_str!("prelude.lox");
+
 fn evaluate_arithmetic(operator: &Token, left: &Literal, right: &Literal) -> EvaluationResult {
     match (left, right) {
         (Literal::Number(left), Literal::Number(right)) => match operator.token_type {
             TokenType::Plus => Ok(Literal::Number(left + right)),
             TokenType::Minus => Ok(Literal::Number(left - right)),
+            TokenType::Slash | TokenType::Percent if *right == 0.0 => Err(LoxError::new(
+                operator,
+                LoxErrorType::RuntimeError(DetailedErrorType::DivisionByZero),
+            )),
             TokenType::Slash => Ok(Literal::Number(left / right)),
             TokenType::Star => Ok(Literal::Number(left * right)),
+            // Truncated like C's `fmod`: the result takes the sign of the
+            // dividend, so `-7 % 3` is -1 and `7 % -3` is 1.
+            TokenType::Percent => Ok(Literal::Number(left % right)),
+            TokenType::StarStar => Ok(Literal::Number(left.powf(*right))),
+            TokenType::LessLess | TokenType::GreaterGreater => {
+                evaluate_shift(operator, *left, *right)
+            }
             _ => panic!(),
         },
 
-        _ => Err(LoxError::new(
-            &operator,
-            LoxErrorType::RuntimeError(DetailedErrorType::ExpectedNumber),
-        )),
+        _ => Err(expected_number(operator, left, right)),
+    }
+}
+
+/// The error for a binary operator that needs numbers, naming the type of
+/// the first operand that isn't one.
+fn expected_number(operator: &Token, left: &Literal, right: &Literal) -> LoxError {
+    let culprit = match left {
+        Literal::Number(_) => right,
+        _ => left,
+    };
+    return LoxError::new(
+        operator,
+        LoxErrorType::RuntimeError(DetailedErrorType::ExpectedNumber(culprit.type_name())),
+    );
+}
+
+/// Shifts operate on the operands truncated to 64-bit two's complement
+/// integers. Bits shifted past either end are discarded instead of
+/// wrapping the shift amount, so `1 << 63` is the most negative integer,
+/// any `<<` by 64 or more gives 0, and `>>` by 64 or more leaves only the
+/// sign (0 or -1). A negative shift amount is a runtime error.
+fn evaluate_shift(operator: &Token, left: f64, right: f64) -> EvaluationResult {
+    if right < 0.0 {
+        return Err(LoxError::new(
+            operator,
+            LoxErrorType::RuntimeError(DetailedErrorType::NegativeShiftAmount),
+        ));
     }
+    let value = left as i64;
+    let amount = right.min(63.0) as u32;
+    let result = match operator.token_type {
+        TokenType::LessLess if right >= 64.0 => 0,
+        TokenType::LessLess => value << amount,
+        _ => value >> amount,
+    };
+    Ok(Literal::Number(result as f64))
 }
 
 fn evaluate_comparison(operator: &Token, left: &Literal, right: &Literal) -> EvaluationResult {
@@ -42,54 +100,279 @@ fn evaluate_comparison(operator: &Token, left: &Literal, right: &Literal) -> Eva
             TokenType::GreaterEqual => Ok(Literal::Boolean(left >= right)),
             _ => panic!(),
         },
+        // Strings compare lexicographically by code point.
+        (Literal::String(left), Literal::String(right)) => match operator.token_type {
+            TokenType::Less => Ok(Literal::Boolean(left < right)),
+            TokenType::LessEqual => Ok(Literal::Boolean(left <= right)),
+            TokenType::Greater => Ok(Literal::Boolean(left > right)),
+            TokenType::GreaterEqual => Ok(Literal::Boolean(left >= right)),
+            _ => panic!(),
+        },
 
-        _ => Err(LoxError::new(
-            &operator,
-            LoxErrorType::RuntimeError(DetailedErrorType::ExpectedNumber),
-        )),
+        _ => Err(expected_number(operator, left, right)),
     }
 }
 
 pub struct Interpreter {
     pub globals: Rc<RefCell<Environment>>,
     environment: Rc<RefCell<Environment>>,
+    /// How many scopes out each resolved local reference finds its
+    /// binding, kept per source so programs that happen to put a token at
+    /// the same position never see each other's resolution. Code that was
+    /// never resolved is looked up by walking the environments.
+    locals: HashMap<usize, Rc<ResolutionMap>>,
+    call_depth: usize,
+    /// Log every statement to stderr just before it runs.
+    pub trace: bool,
+    /// Make `breakpoint()` stop and open a debugger prompt.
+    pub debug: bool,
+    /// The longest string, in bytes, that `+` may produce. Unlimited when
+    /// `None`.
+    pub max_string_length: Option<usize>,
+    /// Let scripts read and write files. Off unless the embedder opts in.
+    pub allow_filesystem: bool,
+    /// Let scripts read environment variables. Off unless the embedder
+    /// opts in.
+    pub allow_environment: bool,
+    /// Where `print` writes.
+    output: Box<dyn Write>,
+    /// The time since the Unix epoch, as read by `clock()` and `millis()`.
+    /// Replaceable so tests can control time.
+    pub clock: Box<dyn Fn() -> Duration>,
+    /// Looks up an environment variable for `env()`. Replaceable so tests
+    /// don't have to change the process environment.
+    pub env_var: EnvLookup,
+    /// Where `breakpoint()` reads debugger commands. Replaceable so tests
+    /// can script a debugging session.
+    pub read_line: LineReader,
+    /// The source of `random()` and `randomInt()`.
+    pub rng: Rng,
+    /// Tallies of `assert()` calls, reported by `testSummary()`.
+    pub assertions: AssertionCounts,
+    /// How `print` spells booleans and nil.
+    pub display: DisplayFormat,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AssertionCounts {
+    pub run: usize,
+    pub passed: usize,
+}
+
+/// The words `print` writes for `true`, `false` and `nil`. Defaults to the
+/// reference spellings. Only a printed value itself is affected, not one
+/// inside a string, array or map.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DisplayFormat {
+    pub true_text: String,
+    pub false_text: String,
+    pub nil_text: String,
+}
+
+impl Default for DisplayFormat {
+    fn default() -> Self {
+        Self {
+            true_text: "true".to_string(),
+            false_text: "false".to_string(),
+            nil_text: "nil".to_string(),
+        }
+    }
+}
+
+impl DisplayFormat {
+    /// Renders a value as `print` shows it.
+    pub fn format(&self, value: &Literal) -> String {
+        match value {
+            Literal::Boolean(true) => self.true_text.clone(),
+            Literal::Boolean(false) => self.false_text.clone(),
+            Literal::Nil => self.nil_text.clone(),
+            other => other.to_string(),
+        }
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Interpreter {
     pub fn new() -> Self {
-        let globals = Rc::new(RefCell::new(Environment::new()));
+        Self::with_output(io::stdout())
+    }
 
-        let clock = Literal::Function(Function::Native {
-            arity: 0,
-            body: Box::new(|_args: &Vec<Literal>| {
-                Literal::Number(
-                    SystemTime::now()
-                        .duration_since(UNIX_EPOCH)
-                        .unwrap()
-                        .as_secs_f64(),
-                )
-            }),
-        });
+    /// Creates an interpreter whose `print` statements write to `output`
+    /// instead of stdout.
+    pub fn with_output(output: impl Write + 'static) -> Self {
+        let mut interpreter = Self::bare_with_output(output);
+        natives::define_natives(&mut interpreter.globals.borrow_mut());
+        let prelude = crate::compile(PRELUDE).expect("the prelude should parse");
+        interpreter
+            .interpret(&prelude)
+            .expect("the prelude should run");
+        return interpreter;
+    }
+
+    /// Creates an interpreter whose random numbers come out the same on
+    /// every run with the same seed.
+    pub fn with_seed(seed: u64) -> Self {
+        let mut interpreter = Self::new();
+        interpreter.rng = Rng::from_seed(seed);
+        return interpreter;
+    }
 
-        globals.borrow_mut().define("clock".to_owned(), clock);
+    /// Creates an interpreter with no native functions or prelude defined,
+    /// for embedders who want to choose exactly what scripts can call.
+    pub fn bare() -> Self {
+        Self::bare_with_output(io::stdout())
+    }
+
+    fn bare_with_output(output: impl Write + 'static) -> Self {
+        let globals = Rc::new(RefCell::new(Environment::new()));
+
+        // Command-line arguments for the script; empty unless the CLI
+        // passes some along.
+        globals
+            .borrow_mut()
+            .define("args", Literal::array(Vec::new()));
 
         Self {
             globals: Rc::clone(&globals),
             environment: Rc::clone(&globals),
+            locals: HashMap::new(),
+            call_depth: 0,
+            trace: false,
+            debug: false,
+            max_string_length: None,
+            allow_filesystem: false,
+            allow_environment: false,
+            output: Box::new(output),
+            clock: Box::new(natives::system_clock),
+            env_var: Box::new(natives::process_env_var),
+            read_line: Box::new(natives::stdin_line),
+            rng: Rng::from_entropy(),
+            assertions: AssertionCounts::default(),
+            display: DisplayFormat::default(),
+        }
+    }
+
+    /// Makes a Rust closure callable from Lox as a global function. A plain
+    /// number for `arity` means exactly that many arguments.
+    pub fn define_native_fn(
+        &mut self,
+        name: &str,
+        arity: impl Into<Arity>,
+        body: impl Fn(&mut Interpreter, &[Literal]) -> NativeResult + 'static,
+    ) {
+        self.globals.borrow_mut().define(
+            name,
+            Literal::Function(Function::Native {
+                arity: arity.into(),
+                body: Rc::new(body),
+            }),
+        );
+    }
+
+    /// The sorted names of the global native functions.
+    pub fn native_names(&self) -> Vec<String> {
+        let mut names: Vec<_> = self
+            .globals
+            .borrow()
+            .iter()
+            .filter(|(_, value)| matches!(value, Literal::Function(Function::Native { .. })))
+            .map(|(name, _)| name.to_string())
+            .collect();
+        names.sort();
+        return names;
+    }
+
+    /// The number of Lox function calls currently in progress: 0 at the top
+    /// level, 1 inside a function called from there, and so on. Natives do
+    /// not open a frame of their own.
+    pub fn call_depth(&self) -> usize {
+        self.call_depth
+    }
+
+    /// Runs a top-level program, returning the value of the last statement.
+    /// A `return` that unwinds all the way out here was not inside any
+    /// function, so it is reported as a runtime error at the keyword.
+    pub fn interpret(&mut self, statements: &[Stmt]) -> EvaluationResult {
+        let mut last = Literal::Nil;
+        for stmt in statements {
+            last = match self.execute(stmt) {
+                Ok(value) => value,
+                Err(LoxError {
+                    kind: LoxErrorType::Return(_),
+                    token,
+                    ..
+                }) => {
+                    return Err(LoxError::new(
+                        &token,
+                        LoxErrorType::RuntimeError(DetailedErrorType::ReturnOutsideFunction),
+                    ))
+                }
+                Err(reason) => return Err(reason),
+            };
+        }
+        Ok(last)
+    }
+
+    /// Records where the local references of a program about to run are
+    /// bound, as found by the resolver. Maps from several programs can be
+    /// added to one interpreter.
+    pub fn resolve(&mut self, locals: ResolutionMap) {
+        for (token, resolution) in locals {
+            let source = self.locals.entry(token.source_id).or_default();
+            Rc::make_mut(source).insert(token, resolution);
+        }
+    }
+
+    /// Runs a program produced by `lox::compile` or `lox::compile_resolved`,
+    /// using its resolution. The same program can be run repeatedly without
+    /// scanning, parsing or resolving it again.
+    pub fn run_compiled(&mut self, program: &crate::Program) -> EvaluationResult {
+        if let Some(token) = program.locals.keys().next() {
+            self.locals
+                .insert(token.source_id, Rc::clone(&program.locals));
         }
+        return self.interpret(program);
+    }
+
+    fn resolution(&self, identifier: &Token) -> Option<Resolution> {
+        return self
+            .locals
+            .get(&identifier.source_id)?
+            .get(identifier)
+            .copied();
     }
 
-    pub fn execute<'b>(&mut self, stmt: &Stmt) -> EvaluationResult {
+    pub fn execute(&mut self, stmt: &Stmt) -> EvaluationResult {
+        if self.trace {
+            eprintln!("[line {}] {:?}", stmt.line(), stmt);
+        }
         match stmt {
             Stmt::Print(expr) => self.execute_print(expr),
             Stmt::Expression(expr) => self.evaluate(expr),
             Stmt::If(condition, then_branch, else_branch) => {
                 self.execute_if(condition, then_branch, else_branch)
             }
-            Stmt::While(condition, body) => self.execute_while(condition, body),
+            Stmt::While(condition, body) => self.execute_while(condition, body, None),
+            Stmt::For(initializer, condition, increment, body) => {
+                self.execute_for(initializer.as_deref(), condition, increment.as_ref(), body)
+            }
             Stmt::Var(identifier, initializer) => self.define_var(identifier, initializer),
             Stmt::Function(name, params, body) => self.define_function(name, params, body),
-            Stmt::Block(statements) => {
+            Stmt::Class(name, superclass, methods) => self.define_class(name, superclass, methods),
+            // A block that declares nothing has nothing to scope, so it runs
+            // in the enclosing environment rather than allocating its own.
+            Stmt::Block(statements, _) if !statements.iter().any(Stmt::is_declaration) => {
+                for stmt in statements {
+                    self.execute(stmt)?;
+                }
+                Ok(Literal::Nil)
+            }
+            Stmt::Block(statements, _) => {
                 let env = Environment::enclose(&self.environment);
                 self.execute_block(statements, Rc::new(RefCell::new(env)))
             }
@@ -100,19 +383,21 @@ impl Interpreter {
                 };
                 Err(LoxError::new(keyword, LoxErrorType::Return(value)))
             }
+            Stmt::Break(keyword) => Err(LoxError::new(keyword, LoxErrorType::Break)),
+            Stmt::Continue(keyword) => Err(LoxError::new(keyword, LoxErrorType::Continue)),
         }
     }
 
     pub fn execute_block(
         &mut self,
-        statements: &Vec<Stmt>,
+        statements: &[Stmt],
         env: Rc<RefCell<Environment>>,
     ) -> EvaluationResult {
         let previous = self.environment.clone();
         self.environment = env;
 
         for stmt in statements {
-            match self.execute(&stmt) {
+            match self.execute(stmt) {
                 Ok(_) => (),
                 Err(reason) => {
                     self.environment = previous;
@@ -124,36 +409,127 @@ impl Interpreter {
         return Ok(Literal::Nil);
     }
 
+    /// Runs the body of a Lox function in `env`, counting it as an active
+    /// call for the duration.
+    pub fn execute_function_body(
+        &mut self,
+        body: &[Stmt],
+        env: Rc<RefCell<Environment>>,
+    ) -> EvaluationResult {
+        self.call_depth += 1;
+        let result = self.execute_block(body, env);
+        self.call_depth -= 1;
+        result
+    }
+
     fn execute_print(&mut self, expr: &Expr) -> EvaluationResult {
         let value = self.evaluate(expr)?;
-        println!("{}", value);
+        let text = self.display.format(&value);
+        self.write_line(&text);
         Ok(Literal::Nil)
     }
 
+    /// Writes a line to wherever `print` goes.
+    pub fn write_line(&mut self, line: &str) {
+        writeln!(self.output, "{}", line).expect("failed to write output");
+    }
+
+    /// Writes text to wherever `print` goes, without ending the line, and
+    /// flushes it so a prompt shows before input is read.
+    pub fn write_prompt(&mut self, text: &str) {
+        write!(self.output, "{}", text).expect("failed to write output");
+        self.output.flush().expect("failed to write output");
+    }
+
     fn execute_if(
         &mut self,
         condition: &Expr,
-        then_branch: &Box<Stmt>,
+        then_branch: &Stmt,
         else_branch: &Option<Box<Stmt>>,
     ) -> EvaluationResult {
         let value = self.evaluate(condition)?;
         if value.is_truthy() {
-            return self.execute(&*then_branch);
+            return self.execute(then_branch);
         }
         if let Some(else_branch) = else_branch {
-            return self.execute(&*else_branch);
+            return self.execute(else_branch);
         }
         return Ok(Literal::Nil);
     }
 
-    fn execute_while(&mut self, condition: &Expr, body: &Box<Stmt>) -> EvaluationResult {
-        let body = &*body;
+    /// Runs a loop, checking `condition` before each iteration and running
+    /// `increment`, if any, after each one. `break` ends the loop and
+    /// `continue` moves straight on to the increment.
+    fn execute_while(
+        &mut self,
+        condition: &Expr,
+        body: &Stmt,
+        increment: Option<&Expr>,
+    ) -> EvaluationResult {
         while self.evaluate(condition)?.is_truthy() {
-            self.execute(body)?;
+            match self.execute(body) {
+                Err(LoxError {
+                    kind: LoxErrorType::Break,
+                    ..
+                }) => break,
+                Err(LoxError {
+                    kind: LoxErrorType::Continue,
+                    ..
+                }) => (),
+                other => {
+                    other?;
+                }
+            };
+            if let Some(increment) = increment {
+                self.evaluate(increment)?;
+            }
         }
         Ok(Literal::Nil)
     }
 
+    /// `string * count`: the string repeated `count` times, which must be a
+    /// whole number no less than zero.
+    fn repeat_string(&self, operator: &Token, s: &str, count: f64) -> EvaluationResult {
+        if count < 0.0 || count.fract() != 0.0 {
+            return Err(LoxError::new(
+                operator,
+                LoxErrorType::RuntimeError(DetailedErrorType::InvalidRepetitionCount),
+            ));
+        }
+        let length = s.len() as f64 * count;
+        if self
+            .max_string_length
+            .is_some_and(|max| length > max as f64)
+        {
+            return Err(LoxError::new(
+                operator,
+                LoxErrorType::RuntimeError(DetailedErrorType::StringTooLong),
+            ));
+        }
+        return Ok(Literal::string(s.repeat(count as usize)));
+    }
+
+    /// Runs a `for` loop. The initializer runs once, in a scope enclosing
+    /// the whole loop.
+    fn execute_for(
+        &mut self,
+        initializer: Option<&Stmt>,
+        condition: &Expr,
+        increment: Option<&Expr>,
+        body: &Stmt,
+    ) -> EvaluationResult {
+        let Some(initializer) = initializer else {
+            return self.execute_while(condition, body, increment);
+        };
+        let previous = Rc::clone(&self.environment);
+        self.environment = Rc::new(RefCell::new(Environment::enclose(&previous)));
+        let result = self
+            .execute(initializer)
+            .and_then(|_| self.execute_while(condition, body, increment));
+        self.environment = previous;
+        return result;
+    }
+
     fn define_var(&mut self, identifier: &Token, initializer: &Option<Expr>) -> EvaluationResult {
         let value = match initializer {
             Some(initializer) => self.evaluate(initializer)?,
@@ -168,25 +544,81 @@ impl Interpreter {
     fn define_function(
         &mut self,
         name: &Token,
-        params: &Vec<Token>,
-        body: &Vec<Stmt>,
+        params: &[Parameter],
+        body: &[Stmt],
     ) -> EvaluationResult {
         self.environment.borrow_mut().define(
             name.lexeme.clone(),
             Literal::Function(Function::Lox {
-                arity: params.len(),
-                params: Box::new(params.clone()),
-                body: Box::new(body.clone()),
+                params: params.to_vec(),
+                body: body.into(),
                 closure: self.environment.clone(),
             }),
         );
         Ok(Literal::Nil)
     }
 
+    /// Methods close over an environment binding `super` when the class has
+    /// a superclass.
+    fn define_class(
+        &mut self,
+        name: &Token,
+        superclass: &Option<Expr>,
+        methods: &[Stmt],
+    ) -> EvaluationResult {
+        let superclass = match superclass {
+            None => None,
+            Some(expr) => match self.evaluate(expr)? {
+                Literal::Class(class) => Some(class),
+                _ => {
+                    return Err(LoxError::new(
+                        name,
+                        LoxErrorType::RuntimeError(DetailedErrorType::SuperclassMustBeAClass),
+                    ))
+                }
+            },
+        };
+        let closure = match &superclass {
+            None => self.environment.clone(),
+            Some(superclass) => {
+                let mut env = Environment::enclose(&self.environment);
+                env.define("super", Literal::Class(Rc::clone(superclass)));
+                Rc::new(RefCell::new(env))
+            }
+        };
+        let mut class = Class {
+            name: name.lexeme.clone(),
+            superclass,
+            methods: HashMap::new(),
+        };
+        for method in methods {
+            if let Stmt::Function(method_name, params, body) = method {
+                let function = Function::Lox {
+                    params: params.to_vec(),
+                    body: body.as_slice().into(),
+                    closure: Rc::clone(&closure),
+                };
+                class.methods.insert(method_name.lexeme.clone(), function);
+            }
+        }
+        self.environment
+            .borrow_mut()
+            .define(name.lexeme.clone(), Literal::Class(Rc::new(class)));
+        Ok(Literal::Nil)
+    }
+
+    pub fn evaluate_in(&mut self, expr: &Expr, env: Rc<RefCell<Environment>>) -> EvaluationResult {
+        let previous = std::mem::replace(&mut self.environment, env);
+        let result = self.evaluate(expr);
+        self.environment = previous;
+        result
+    }
+
     pub fn evaluate(&mut self, expr: &Expr) -> EvaluationResult {
         match expr {
-            Expr::Literal(value) => Ok(value.clone()),
-            Expr::Grouping(expr) => self.evaluate(expr),
+            Expr::Literal(value, _) => Ok(value.clone()),
+            Expr::Grouping(expr, _) => self.evaluate(expr),
+            Expr::Interpolation(parts, start) => self.evaluate_interpolation(parts, start),
             Expr::Unary(operator, right) => self.evaluate_unary_expression(operator, right),
             Expr::Binary(left, operator, right) => {
                 self.evaluate_binary_expression(left, operator, right)
@@ -194,44 +626,187 @@ impl Interpreter {
             Expr::Var(identifier) => self.evaluate_var(identifier),
             Expr::Assign(identifier, expr) => self.evaluate_assignment(identifier, expr),
             Expr::Logical(left, operator, right) => self.evaluate_logical(left, operator, right),
-            Expr::Call(callee, paren, arguments) => self.evaluate_call(callee, paren, arguments),
+            Expr::Call(callee, paren, arguments, keywords) => {
+                self.evaluate_call(callee, paren, arguments, keywords)
+            }
+            Expr::Function(_, params, body) => Ok(Literal::Function(Function::Lox {
+                params: params.clone(),
+                body: body.as_slice().into(),
+                closure: Rc::clone(&self.environment),
+            })),
+            Expr::Get(object, name) => self.evaluate_get(object, name),
+            Expr::Set(object, name, value) => self.evaluate_set(object, name, value),
+            Expr::This(keyword) => self.evaluate_var(keyword),
+            Expr::Super(keyword, method) => self.evaluate_super(keyword, method),
+            Expr::Ternary(condition, then_branch, else_branch) => {
+                if self.evaluate(condition)?.is_truthy() {
+                    self.evaluate(then_branch)
+                } else {
+                    self.evaluate(else_branch)
+                }
+            }
+        }
+    }
+
+    /// Looks a property up on an instance: its own fields first, then its
+    /// class's methods.
+    fn evaluate_get(&mut self, object: &Expr, name: &Token) -> EvaluationResult {
+        let instance = match self.evaluate(object)? {
+            Literal::Instance(instance) => instance,
+            _ => {
+                return Err(LoxError::new(
+                    name,
+                    LoxErrorType::RuntimeError(DetailedErrorType::OnlyInstancesHaveProperties),
+                ))
+            }
+        };
+        if let Some(value) = instance.borrow().fields.get(&name.lexeme) {
+            return Ok(value.clone());
+        }
+        let class = Rc::clone(&instance.borrow().class);
+        match class.find_method(&name.lexeme) {
+            Some(method) => Ok(Literal::Function(method.bind(Literal::Instance(instance)))),
+            None => Err(LoxError::new(
+                name,
+                LoxErrorType::RuntimeError(DetailedErrorType::UndefinedProperty(
+                    name.lexeme.clone(),
+                )),
+            )),
+        }
+    }
+
+    /// Looks `method` up starting at the superclass of the class whose
+    /// method is running, bound to the current `this`.
+    fn evaluate_super(&mut self, keyword: &Token, method: &Token) -> EvaluationResult {
+        let superclass = match self.evaluate_var(keyword)? {
+            Literal::Class(class) => class,
+            _ => unreachable!("'super' is only ever bound to a class"),
+        };
+        let this = self.environment.borrow().fetch("this");
+        match (superclass.find_method(&method.lexeme), this) {
+            (Some(found), Some(this)) => Ok(Literal::Function(found.bind(this))),
+            _ => Err(LoxError::new(
+                method,
+                LoxErrorType::RuntimeError(DetailedErrorType::UndefinedProperty(
+                    method.lexeme.clone(),
+                )),
+            )),
+        }
+    }
+
+    /// Stores a field on an instance, creating it if needed, and returns the
+    /// value stored.
+    fn evaluate_set(&mut self, object: &Expr, name: &Token, value: &Expr) -> EvaluationResult {
+        let instance = match self.evaluate(object)? {
+            Literal::Instance(instance) => instance,
+            _ => {
+                return Err(LoxError::new(
+                    name,
+                    LoxErrorType::RuntimeError(DetailedErrorType::OnlyInstancesHaveFields),
+                ))
+            }
+        };
+        if instance.borrow().frozen {
+            return Err(LoxError::new(
+                name,
+                LoxErrorType::RuntimeError(DetailedErrorType::FrozenInstance),
+            ));
+        }
+        let value = self.evaluate(value)?;
+        instance
+            .borrow_mut()
+            .fields
+            .insert(name.lexeme.clone(), value.clone());
+        Ok(value)
+    }
+
+    fn evaluate_interpolation(&mut self, parts: &[Expr], start: &Token) -> EvaluationResult {
+        let mut result = String::new();
+        for part in parts {
+            result.push_str(&self.evaluate(part)?.to_string());
+            if self.max_string_length.is_some_and(|max| result.len() > max) {
+                return Err(LoxError::new(
+                    start,
+                    LoxErrorType::RuntimeError(DetailedErrorType::StringTooLong),
+                ));
+            }
         }
+        Ok(Literal::string(result))
     }
 
     fn evaluate_var(&mut self, identifier: &Token) -> EvaluationResult {
-        match self.environment.borrow().fetch(&identifier.lexeme) {
+        let name = &identifier.lexeme;
+        let found = match self.resolution(identifier) {
+            Some(Resolution::Local(distance)) => {
+                let environment = self.environment.borrow();
+                environment
+                    .fetch_at(distance, name)
+                    .or_else(|| environment.fetch(name))
+            }
+            Some(Resolution::Global) => self.globals.borrow().fetch(name),
+            None => self.environment.borrow().fetch(name),
+        };
+        match found {
             Some(value) => Ok(value.to_owned()),
             None => Err(LoxError::new(
-                &identifier,
-                LoxErrorType::RuntimeError(DetailedErrorType::UndeclaredIdentifier),
+                identifier,
+                LoxErrorType::RuntimeError(DetailedErrorType::UndeclaredIdentifier(
+                    identifier.lexeme.clone(),
+                )),
             )),
         }
     }
 
     fn evaluate_call(
         &mut self,
-        callee: &Box<Expr>,
+        callee: &Expr,
         paren: &Token,
-        arguments: &Vec<Expr>,
+        arguments: &[Expr],
+        keywords: &[(Token, Expr)],
     ) -> EvaluationResult {
-        let callee = self.evaluate(&callee)?;
+        let callee = self.evaluate(callee)?;
         let mut args = Vec::new();
 
         for arg in arguments {
             args.push(self.evaluate(arg)?);
         }
 
+        let mut keyword_args = Vec::new();
+        for (name, arg) in keywords {
+            keyword_args.push((name.clone(), self.evaluate(arg)?));
+        }
+
         let arity = args.len();
 
         match callee {
+            Literal::Function(fun) if !keyword_args.is_empty() => {
+                fun.call_with_keywords(self, paren, args, keyword_args)
+            }
             Literal::Function(fun) => {
-                if fun.arity() != arity {
+                if !fun.accepts(arity) {
+                    return Err(LoxError::new(
+                        paren,
+                        LoxErrorType::RuntimeError(DetailedErrorType::InvalidArity(
+                            fun.expected_arity(),
+                            arity,
+                        )),
+                    ));
+                }
+                fun.call(self, paren, &args)
+            }
+            Literal::Class(class) => {
+                if arity != 0 || !keyword_args.is_empty() {
                     return Err(LoxError::new(
                         paren,
-                        LoxErrorType::RuntimeError(DetailedErrorType::InvalidArity),
+                        LoxErrorType::RuntimeError(DetailedErrorType::InvalidArity(
+                            Arity::Exactly(0),
+                            arity + keyword_args.len(),
+                        )),
                     ));
                 }
-                fun.call(self, &args)
+                Ok(Literal::Instance(Rc::new(RefCell::new(Instance::new(
+                    &class,
+                )))))
             }
             _ => Err(LoxError::new(
                 paren,
@@ -242,11 +817,11 @@ impl Interpreter {
 
     fn evaluate_logical(
         &mut self,
-        left: &Box<Expr>,
+        left: &Expr,
         operator: &Token,
-        right: &Box<Expr>,
+        right: &Expr,
     ) -> EvaluationResult {
-        let value = self.evaluate(&*left)?;
+        let value = self.evaluate(left)?;
         match operator.token_type {
             TokenType::Or => {
                 if value.is_truthy() {
@@ -259,40 +834,48 @@ impl Interpreter {
                 }
             }
         }
-        return self.evaluate(&right);
+        return self.evaluate(right);
     }
 
-    fn evaluate_assignment(&mut self, identifier: &Token, expr: &Box<Expr>) -> EvaluationResult {
-        let value = self.evaluate(&*expr)?;
-        if self
-            .environment
-            .borrow_mut()
-            .assign(&identifier.lexeme, value.clone())
-        {
+    fn evaluate_assignment(&mut self, identifier: &Token, expr: &Expr) -> EvaluationResult {
+        let value = self.evaluate(expr)?;
+        let name = &identifier.lexeme;
+        let assigned = match self.resolution(identifier) {
+            Some(Resolution::Local(distance)) => {
+                let mut environment = self.environment.borrow_mut();
+                environment.assign_at(distance, name, value.clone())
+                    || environment.assign(name, value.clone())
+            }
+            Some(Resolution::Global) => self.globals.borrow_mut().assign(name, value.clone()),
+            None => self.environment.borrow_mut().assign(name, value.clone()),
+        };
+        if assigned {
             Ok(value)
         } else {
             Err(LoxError::new(
-                &identifier,
-                LoxErrorType::RuntimeError(DetailedErrorType::UndeclaredIdentifier),
+                identifier,
+                LoxErrorType::RuntimeError(DetailedErrorType::UndeclaredIdentifier(
+                    identifier.lexeme.clone(),
+                )),
             ))
         }
     }
 
-    fn evaluate_unary_expression(
-        &mut self,
-        operator: &Token,
-        right: &Box<Expr>,
-    ) -> EvaluationResult {
-        let right = self.evaluate(&*right)?;
+    fn evaluate_unary_expression(&mut self, operator: &Token, right: &Expr) -> EvaluationResult {
+        let line = right.line();
+        let right = self.evaluate(right)?;
         match operator.token_type {
             TokenType::Minus => match right {
                 Literal::Number(value) => Ok(Literal::Number(-value)),
                 _ => Err(LoxError::new(
-                    &operator,
-                    LoxErrorType::RuntimeError(DetailedErrorType::ExpectedNumber),
-                )),
+                    operator,
+                    LoxErrorType::RuntimeError(DetailedErrorType::ExpectedNumber(
+                        right.type_name(),
+                    )),
+                )
+                .at_line(line)),
             },
-            TokenType::Bang => return Ok(Literal::Boolean(right.is_truthy())),
+            TokenType::Bang => return Ok(Literal::Boolean(!right.is_truthy())),
             _ => {
                 panic!()
             }
@@ -301,24 +884,75 @@ impl Interpreter {
 
     fn evaluate_binary_expression(
         &mut self,
-        left: &Box<Expr>,
+        left: &Expr,
         operator: &Token,
-        right: &Box<Expr>,
+        right: &Expr,
     ) -> EvaluationResult {
-        let left = self.evaluate(&*left)?;
-        let right = self.evaluate(&*right)?;
+        let (left_line, right_line) = (left.line(), right.line());
+        let left = self.evaluate(left)?;
+        let right = self.evaluate(right)?;
 
-        match operator.token_type {
+        let result = match operator.token_type {
             TokenType::Plus => match (&left, &right) {
                 (Literal::String(left), Literal::String(right)) => {
+                    let length = left.len() + right.len();
+                    if self.max_string_length.is_some_and(|max| length > max) {
+                        return Err(LoxError::new(
+                            operator,
+                            LoxErrorType::RuntimeError(DetailedErrorType::StringTooLong),
+                        ));
+                    }
                     let concatenated = format!("{}{}", left, right);
-                    return Ok(Literal::String(concatenated));
+                    return Ok(Literal::string(concatenated));
+                }
+                (Literal::Number(_), Literal::Number(_)) => {
+                    evaluate_arithmetic(operator, &left, &right)
                 }
+                // A new array; neither operand changes.
+                (Literal::Array(left), Literal::Array(right)) => {
+                    let mut items = left.borrow().clone();
+                    items.extend(right.borrow().iter().cloned());
+                    return Ok(Literal::array(items));
+                }
+                (Literal::Array(_), _) | (_, Literal::Array(_)) => Err(LoxError::new(
+                    operator,
+                    LoxErrorType::RuntimeError(DetailedErrorType::OperandsMustBeTwoArrays),
+                )),
+                // A new map with the entries of both. Where both have a key,
+                // the right-hand value wins.
+                (Literal::Map(left), Literal::Map(right)) => {
+                    let mut entries = left.borrow().clone();
+                    entries.extend(
+                        right
+                            .borrow()
+                            .iter()
+                            .map(|(key, value)| (key.clone(), value.clone())),
+                    );
+                    return Ok(Literal::map(entries));
+                }
+                (Literal::Map(_), _) | (_, Literal::Map(_)) => Err(LoxError::new(
+                    operator,
+                    LoxErrorType::RuntimeError(DetailedErrorType::OperandsMustBeTwoMaps),
+                )),
+                _ => Err(LoxError::new(
+                    operator,
+                    LoxErrorType::RuntimeError(DetailedErrorType::OperandsMustBeAddable(
+                        left.type_name(),
+                        right.type_name(),
+                    )),
+                )),
+            },
+            TokenType::Star => match (&left, &right) {
+                (Literal::String(s), Literal::Number(n))
+                | (Literal::Number(n), Literal::String(s)) => self.repeat_string(operator, s, *n),
                 _ => evaluate_arithmetic(operator, &left, &right),
             },
-            TokenType::Minus | TokenType::Star | TokenType::Slash => {
-                evaluate_arithmetic(operator, &left, &right)
-            }
+            TokenType::Minus
+            | TokenType::Slash
+            | TokenType::Percent
+            | TokenType::StarStar
+            | TokenType::LessLess
+            | TokenType::GreaterGreater => evaluate_arithmetic(operator, &left, &right),
             TokenType::Greater
             | TokenType::GreaterEqual
             | TokenType::Less
@@ -326,6 +960,1497 @@ impl Interpreter {
             TokenType::EqualEqual => Ok(Literal::Boolean(left == right)),
             TokenType::BangEqual => Ok(Literal::Boolean(left != right)),
             _ => panic!(),
-        }
+        };
+        // A type error points at the operand at fault, which may start on a
+        // later line than the operator.
+        return result.map_err(|error| match error.kind {
+            LoxErrorType::RuntimeError(DetailedErrorType::ExpectedNumber(_)) => match left {
+                Literal::Number(_) => error.at_line(right_line),
+                _ => error.at_line(left_line),
+            },
+            _ => error,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, resolver::Resolver, scanner::Scanner};
+
+    fn run(source: &str) -> EvaluationResult {
+        run_with(&mut Interpreter::new(), source)
+    }
+
+    fn run_with(interpreter: &mut Interpreter, source: &str) -> EvaluationResult {
+        let tokens = Scanner::new(source.to_string()).scan_tokens().unwrap();
+        let statements = Parser::new(tokens).parse().unwrap();
+        let locals = Resolver::new().resolve_statements(&statements).unwrap();
+        interpreter.resolve(locals);
+        interpreter.interpret(&statements)
+    }
+
+    #[test]
+    fn test_return_inside_function() {
+        let result = run("fun f() { return 42; } f();");
+        assert_eq!(Literal::Number(42.0), result.unwrap());
+    }
+
+    #[test]
+    fn test_early_and_bare_return() {
+        let source = "
+            fun sign(n) {
+                if (n < 0) return -1;
+                if (n == 0) { return 0; }
+                return 1;
+            }
+            sign(-5) * 100 + sign(0) * 10 + sign(7);";
+        assert_eq!(Literal::Number(-99.0), run(source).unwrap());
+        assert_eq!(
+            Literal::Nil,
+            run("fun f() { return; print \"unreachable\"; } f();").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_return_outside_function() {
+        // The resolver rejects this, so run it unresolved to check that the
+        // interpreter still reports it rather than leaking the signal.
+        let tokens = Scanner::new("var a = 1;\n\nreturn a;".to_string())
+            .scan_tokens()
+            .unwrap();
+        let statements = Parser::new(tokens).parse().unwrap();
+        let err = Interpreter::new().interpret(&statements).unwrap_err();
+        assert_eq!(
+            LoxErrorType::RuntimeError(DetailedErrorType::ReturnOutsideFunction),
+            err.kind
+        );
+        assert_eq!(3, err.line);
+        assert_eq!("return", err.token.lexeme);
+        assert_eq!(
+            "Can't return from top-level code.\n[line 3]",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn test_default_parameters() {
+        let source = "fun f(a, b = 2, c = a + b) { return a + b + c; }";
+        assert_eq!(
+            Literal::Number(6.0),
+            run(&format!("{} f(1);", source)).unwrap()
+        );
+        assert_eq!(
+            Literal::Number(12.0),
+            run(&format!("{} f(1, 5);", source)).unwrap()
+        );
+        assert_eq!(
+            Literal::Number(9.0),
+            run(&format!("{} f(1, 5, 3);", source)).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_exact_arity_without_defaults() {
+        let err = run("fun f(a, b) { return a; } f(1);").unwrap_err();
+        assert_eq!(
+            LoxErrorType::RuntimeError(DetailedErrorType::InvalidArity(Arity::Exactly(2), 1)),
+            err.kind
+        );
+
+        let err = run("fun f(a, b = 1) { return a; } f(1, 2, 3);").unwrap_err();
+        assert_eq!(
+            LoxErrorType::RuntimeError(DetailedErrorType::InvalidArity(Arity::Between(1, 2), 3)),
+            err.kind
+        );
+    }
+
+    #[test]
+    fn test_rest_parameter() {
+        let source = "fun sum(first, ...rest) { return rest; }";
+        let rest = run(&format!("{} sum(1, 2, 3);", source)).unwrap();
+        assert_eq!(
+            Literal::array(vec![Literal::Number(2.0), Literal::Number(3.0)]),
+            rest
+        );
+        assert_eq!("[2, 3]", rest.to_string());
+
+        let rest = run(&format!("{} sum(1);", source)).unwrap();
+        assert_eq!("[]", rest.to_string());
+
+        let err = run(&format!("{} sum();", source)).unwrap_err();
+        assert_eq!(
+            LoxErrorType::RuntimeError(DetailedErrorType::InvalidArity(Arity::AtLeast(1), 0)),
+            err.kind
+        );
+    }
+
+    #[test]
+    fn test_keyword_arguments() {
+        let source = "fun f(x, y, z = 3) { return x * 100 + y * 10 + z; }";
+        assert_eq!(
+            Literal::Number(123.0),
+            run(&format!("{} f(y: 2, x: 1);", source)).unwrap()
+        );
+        assert_eq!(
+            Literal::Number(125.0),
+            run(&format!("{} f(1, z: 5, y: 2);", source)).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_keyword_argument_errors() {
+        let source = "fun f(x, y = 2) { return x + y; }";
+
+        let err = run(&format!("{} f(1, w: 2);", source)).unwrap_err();
+        assert_eq!(
+            LoxErrorType::RuntimeError(DetailedErrorType::UnknownKeywordArgument),
+            err.kind
+        );
+        assert_eq!("w", err.token.lexeme);
+
+        let err = run(&format!("{} f(1, x: 2);", source)).unwrap_err();
+        assert_eq!(
+            LoxErrorType::RuntimeError(DetailedErrorType::DuplicateArgument),
+            err.kind
+        );
+
+        let err = run(&format!("{} f(y: 2);", source)).unwrap_err();
+        assert_eq!(
+            LoxErrorType::RuntimeError(DetailedErrorType::InvalidArity(Arity::Between(1, 2), 1)),
+            err.kind
+        );
+    }
+
+    #[test]
+    fn test_to_json() {
+        let result = run("fun f(...items) { return toJSON(items); } f(1, \"two\", nil);");
+        assert_eq!(Literal::string("[1,\"two\",null]"), result.unwrap());
+
+        let err = run("fun f() {}\ntoJSON(f);").unwrap_err();
+        assert_eq!(
+            LoxErrorType::RuntimeError(DetailedErrorType::InvalidArgument(
+                "Cannot serialize a function to JSON.".to_string()
+            )),
+            err.kind
+        );
+        assert_eq!(2, err.line);
+    }
+
+    #[test]
+    fn test_string_interpolation() {
+        assert_eq!(
+            Literal::string("Hello, world!"),
+            run("var name = \"world\"; \"Hello, ${name}!\";").unwrap()
+        );
+        assert_eq!(
+            Literal::string("1 + 2 = 3, nested: <inner 6>"),
+            run("var a = 1; \"${a} + 2 = ${a + 2}, nested: ${\"<inner ${a * 6}>\"}\";").unwrap()
+        );
+        assert_eq!(
+            Literal::string("${a}"),
+            run("var a = 1; \"\\${a}\";").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_shift_overflow() {
+        assert_eq!(Literal::Number(1024.0), run("1 << 10;").unwrap());
+        assert_eq!(Literal::Number(i64::MIN as f64), run("1 << 63;").unwrap());
+        assert_eq!(Literal::Number(0.0), run("1 << 64;").unwrap());
+        assert_eq!(Literal::Number(0.0), run("1 << 1000;").unwrap());
+        assert_eq!(Literal::Number(2.0), run("10 >> 2;").unwrap());
+        assert_eq!(Literal::Number(0.0), run("10 >> 1000;").unwrap());
+        assert_eq!(Literal::Number(-1.0), run("-10 >> 1000;").unwrap());
+        assert_eq!(Literal::Number(12.0), run("1 + 2 << 2;").unwrap());
+
+        let err = run("1 << -1;").unwrap_err();
+        assert_eq!(
+            LoxErrorType::RuntimeError(DetailedErrorType::NegativeShiftAmount),
+            err.kind
+        );
+    }
+
+    #[test]
+    fn test_mutual_recursion() {
+        let functions = "
+            fun isEven(n) { if (n == 0) return true; return isOdd(n - 1); }
+            fun isOdd(n) { if (n == 0) return false; return isEven(n - 1); }";
+        assert_eq!(
+            Literal::Boolean(true),
+            run(&format!("{} isEven(10);", functions)).unwrap()
+        );
+        assert_eq!(
+            Literal::Boolean(true),
+            run(&format!(
+                "fun check() {{ {} return isOdd(7); }} check();",
+                functions
+            ))
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_error_line_inside_nested_grouping() {
+        let err = run("var x = (\n  (\n    (1 +\n      -\"a\")));").unwrap_err();
+        assert_eq!(4, err.line);
+
+        // The operator and the operand at fault are on different lines.
+        let err = run("var x = -\n  ((\n    \"a\"));").unwrap_err();
+        assert_eq!(2, err.line);
+        let err = run("var x = 1 +\n  2 -\n  (\n    true);").unwrap_err();
+        assert_eq!(3, err.line);
+        assert_eq!(
+            "Operand must be a number, got boolean.\n[line 3]",
+            err.to_string()
+        );
+        let err = run("var x = (nil)\n  < 2;").unwrap_err();
+        assert_eq!(1, err.line);
+    }
+
+    #[test]
+    fn test_format_number() {
+        let format = |source: &str| match run(source).unwrap() {
+            Literal::String(s) => s.to_string(),
+            other => panic!("expected a string, got {}", other),
+        };
+        assert_eq!("3.14", format("formatNumber(3.14159, 2);"));
+        assert_eq!("2.718", format("formatNumber(2.71828, 3);"));
+        assert_eq!("10.00", format("formatNumber(9.999, 2);"));
+        assert_eq!("5.0", format("formatNumber(5, 1);"));
+        assert_eq!("-1", format("formatNumber(-0.6, 0);"));
+
+        for decimals in ["-1", "1.5"] {
+            let err = run(&format!("formatNumber(1, {});", decimals)).unwrap_err();
+            assert_eq!(
+                LoxErrorType::RuntimeError(DetailedErrorType::InvalidArgument(
+                    "formatNumber expects a whole number of decimals between 0 and 100."
+                        .to_string()
+                )),
+                err.kind
+            );
+        }
+    }
+
+    #[test]
+    fn test_call_depth() {
+        assert_eq!(Literal::Number(0.0), run("callDepth();").unwrap());
+        let source = "
+            fun depthAt(n) {
+                if (n == 0) return callDepth();
+                return depthAt(n - 1);
+            }
+            depthAt(2) - depthAt(1);";
+        assert_eq!(Literal::Number(1.0), run(source).unwrap());
+        assert_eq!(
+            Literal::Number(1.0),
+            run("fun f() { return callDepth(); } f();").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_property_access_on_non_instance() {
+        let err = run("var a = 1;\na.b;").unwrap_err();
+        assert_eq!(
+            LoxErrorType::RuntimeError(DetailedErrorType::OnlyInstancesHaveProperties),
+            err.kind
+        );
+        assert_eq!(2, err.line);
+
+        let err = run("\"str\".b = 1;").unwrap_err();
+        assert_eq!(
+            LoxErrorType::RuntimeError(DetailedErrorType::OnlyInstancesHaveFields),
+            err.kind
+        );
+    }
+
+    #[test]
+    fn test_max_string_length() {
+        let mut interpreter = Interpreter::new();
+        interpreter.max_string_length = Some(8);
+
+        let err =
+            run_with(&mut interpreter, "var s = \"ab\";\nwhile (true) s = s + s;").unwrap_err();
+        assert_eq!(
+            LoxErrorType::RuntimeError(DetailedErrorType::StringTooLong),
+            err.kind
+        );
+        assert_eq!(2, err.line);
+        assert_eq!(
+            Literal::string("abababab"),
+            run_with(&mut interpreter, "s;").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_equality_across_types_is_false() {
+        for source in [
+            "1 == true;",
+            "0 == false;",
+            "\"\" == false;",
+            "nil == false;",
+            "\"1\" == 1;",
+        ] {
+            assert_eq!(Literal::Boolean(false), run(source).unwrap(), "{}", source);
+        }
+        assert_eq!(Literal::Boolean(true), run("nil != false;").unwrap());
+    }
+
+    #[test]
+    fn test_min_of_and_max_of() {
+        let numbers = "var numbers = parseJSON(\"[3, -1.5, 7, 2]\");";
+        assert_eq!(
+            Literal::Number(-1.5),
+            run(&format!("{} minOf(numbers);", numbers)).unwrap()
+        );
+        assert_eq!(
+            Literal::Number(7.0),
+            run(&format!("{} maxOf(numbers);", numbers)).unwrap()
+        );
+
+        let err = run("maxOf(parseJSON(\"[]\"));").unwrap_err();
+        assert_eq!(
+            LoxErrorType::RuntimeError(DetailedErrorType::InvalidArgument(
+                "maxOf expects a non-empty array.".to_string()
+            )),
+            err.kind
+        );
+
+        let err = run("fun f(...xs) { return minOf(xs); } f(1, \"2\");").unwrap_err();
+        assert_eq!(
+            LoxErrorType::RuntimeError(DetailedErrorType::InvalidArgument(
+                "minOf expects an array of numbers.".to_string()
+            )),
+            err.kind
+        );
+    }
+
+    #[test]
+    fn test_min_of_with_nan() {
+        let result = run("fun f(...xs) { return minOf(xs); } f(1, (-1) ** 0.5, -5);").unwrap();
+        match result {
+            Literal::Number(value) => assert!(value.is_nan()),
+            other => panic!("expected a number, got {}", other),
+        }
+    }
+
+    #[test]
+    fn test_zip() {
+        let zipped = |left: &str, right: &str| {
+            let source = format!(
+                "toJSON(zip(parseJSON(\"{}\"), parseJSON(\"{}\")));",
+                left, right
+            );
+            run(&source).unwrap().to_string()
+        };
+        assert_eq!("[[1,3],[2,4]]", zipped("[1, 2]", "[3, 4]"));
+        assert_eq!("[[1,4]]", zipped("[1, 2, 3]", "[4]"));
+        assert_eq!("[]", zipped("[]", "[4]"));
+
+        let err = run("zip(parseJSON(\"[1]\"), 1);").unwrap_err();
+        assert_eq!(
+            LoxErrorType::RuntimeError(DetailedErrorType::InvalidArgument(
+                "zip expects two arrays.".to_string()
+            )),
+            err.kind
+        );
+    }
+
+    #[test]
+    fn test_map_with_number_and_boolean_keys() {
+        let source = "
+            var m = parseJSON(\"{}\");
+            mapSet(m, 1, \"one\");
+            mapSet(m, true, \"yes\");
+            mapSet(m, \"1\", \"string one\");
+            mapSet(m, -0, \"zero\");";
+        let lookup = |key: &str| {
+            run(&format!("{} mapGet(m, {});", source, key))
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!("one", lookup("1"));
+        assert_eq!("one", lookup("0.5 + 0.5"));
+        assert_eq!("yes", lookup("true"));
+        assert_eq!("string one", lookup("\"1\""));
+        assert_eq!("zero", lookup("0"));
+        assert_eq!("nil", lookup("false"));
+    }
+
+    #[test]
+    fn test_unhashable_map_key() {
+        let err = run("fun f() {}\nmapSet(parseJSON(\"{}\"), f, 1);").unwrap_err();
+        assert_eq!(
+            LoxErrorType::RuntimeError(DetailedErrorType::UnhashableKey),
+            err.kind
+        );
+        assert_eq!(2, err.line);
+    }
+
+    #[test]
+    fn test_read_and_write_files() {
+        let path = std::env::temp_dir().join(format!("lox-file-test-{}.txt", std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut interpreter = Interpreter::new();
+        interpreter.allow_filesystem = true;
+
+        let source = format!(
+            "writeFile(\"{0}\", \"line one\nline two\"); readFile(\"{0}\");",
+            path
+        );
+        let result = run_with(&mut interpreter, &source);
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(Literal::string("line one\nline two"), result.unwrap());
+
+        let source = format!("readFile(\"{}\");", path);
+        let err = run_with(&mut interpreter, &source).unwrap_err();
+        assert!(matches!(
+            err.kind,
+            LoxErrorType::RuntimeError(DetailedErrorType::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_filesystem_is_disabled_by_default() {
+        for source in ["readFile(\"x\");", "writeFile(\"x\", \"y\");"] {
+            let err = run(source).unwrap_err();
+            assert_eq!(
+                LoxErrorType::RuntimeError(DetailedErrorType::CapabilityDisabled("Filesystem")),
+                err.kind
+            );
+        }
+    }
+
+    #[test]
+    fn test_env() {
+        let mut interpreter = Interpreter::new();
+        interpreter.allow_environment = true;
+        interpreter.env_var =
+            Box::new(|name| (name == "LOX_ENV_TEST").then(|| "configured".to_string()));
+        assert_eq!(
+            Literal::string("configured"),
+            run_with(&mut interpreter, "env(\"LOX_ENV_TEST\");").unwrap()
+        );
+        assert_eq!(
+            Literal::Nil,
+            run_with(&mut interpreter, "env(\"LOX_ENV_TEST_UNSET\");").unwrap()
+        );
+
+        let err = run("env(\"LOX_ENV_TEST\");").unwrap_err();
+        assert_eq!(
+            LoxErrorType::RuntimeError(DetailedErrorType::CapabilityDisabled("Environment")),
+            err.kind
+        );
+    }
+
+    #[test]
+    fn test_partial() {
+        let add = "fun add(a, b) { return a + b; }";
+        assert_eq!(
+            Literal::Number(15.0),
+            run(&format!("{} partial(add, 10)(5);", add)).unwrap()
+        );
+        assert_eq!(
+            Literal::Number(3.0),
+            run(&format!("{} partial(partial(add, 1), 2)();", add)).unwrap()
+        );
+        assert_eq!(
+            Literal::Number(7.0),
+            run(&format!("{} partial(add, 4)(b: 3);", add)).unwrap()
+        );
+
+        let err = run(&format!("{} partial(add, 10)(5, 6);", add)).unwrap_err();
+        assert_eq!(
+            LoxErrorType::RuntimeError(DetailedErrorType::InvalidArity(Arity::Exactly(1), 2)),
+            err.kind
+        );
+        let err = run(&format!("{} partial(add, 1, 2, 3);", add)).unwrap_err();
+        assert_eq!(
+            LoxErrorType::RuntimeError(DetailedErrorType::InvalidArgument(
+                "partial was given more arguments than the function takes.".to_string()
+            )),
+            err.kind
+        );
+    }
+
+    #[test]
+    fn test_compose() {
+        let functions = "fun double(x) { return x * 2; } fun inc(x) { return x + 1; }";
+        assert_eq!(
+            Literal::Number(8.0),
+            run(&format!("{} compose(double, inc)(3);", functions)).unwrap()
+        );
+        assert_eq!(
+            Literal::Number(7.0),
+            run(&format!("{} compose(inc, double)(3);", functions)).unwrap()
+        );
+        assert_eq!(
+            Literal::string("4"),
+            run(&format!("{} compose(toJSON, double)(2);", functions)).unwrap()
+        );
+
+        for args in ["double, clock", "double, 1", "zip, double"] {
+            let err = run(&format!("{} compose({});", functions, args)).unwrap_err();
+            assert_eq!(
+                LoxErrorType::RuntimeError(DetailedErrorType::InvalidArgument(
+                    "compose expects two single-argument functions.".to_string()
+                )),
+                err.kind
+            );
+        }
+    }
+
+    #[test]
+    fn test_identity_and_constant() {
+        assert_eq!(Literal::Number(4.0), run("identity(4);").unwrap());
+        assert_eq!(
+            Literal::Number(6.0),
+            run("compose(identity, partial(partial, identity))(6)();").unwrap()
+        );
+
+        assert_eq!(
+            Literal::string("x"),
+            run("var f = constant(\"x\"); f(); f();").unwrap()
+        );
+        let err = run("constant(1)(2);").unwrap_err();
+        assert_eq!(
+            LoxErrorType::RuntimeError(DetailedErrorType::InvalidArity(Arity::Exactly(0), 1)),
+            err.kind
+        );
+    }
+
+    #[test]
+    fn test_leaked_return_renders_as_internal_error() {
+        let keyword = Token::new(TokenType::Return, "return".to_string(), 7, 0);
+        let err = LoxError::new(&keyword, LoxErrorType::Return(Literal::Number(1.0)));
+        assert_eq!(
+            "Internal error: 'return' escaped its function.\n[line 7]",
+            err.to_string()
+        );
+
+        let source = "fun f(n) { while (true) { if (n > 2) return n; n = n + 1; } } f(0);";
+        assert_eq!(Literal::Number(3.0), run(source).unwrap());
+    }
+
+    #[test]
+    fn test_native_arity_range() {
+        let mut interpreter = Interpreter::new();
+        interpreter.define_native_fn("count", Arity::Between(1, 3), |_, args| {
+            Ok(Literal::Number(args.len() as f64))
+        });
+        for (call, expected) in [("count(1);", 1.0), ("count(1, 2, 3);", 3.0)] {
+            assert_eq!(
+                Literal::Number(expected),
+                run_with(&mut interpreter, call).unwrap()
+            );
+        }
+        for (call, got) in [("count();", 0), ("count(1, 2, 3, 4);", 4)] {
+            let err = run_with(&mut interpreter, call).unwrap_err();
+            assert_eq!(
+                LoxErrorType::RuntimeError(DetailedErrorType::InvalidArity(
+                    Arity::Between(1, 3),
+                    got
+                )),
+                err.kind
+            );
+        }
+    }
+
+    #[test]
+    fn test_millis_uses_the_interpreter_clock() {
+        let now = Rc::new(std::cell::Cell::new(1_700_000_000_000u64));
+        let mut interpreter = Interpreter::new();
+        let ticks = Rc::clone(&now);
+        interpreter.clock = Box::new(move || {
+            ticks.set(ticks.get() + 5);
+            Duration::from_millis(ticks.get())
+        });
+        let source = "var a = millis(); var b = millis(); b - a;";
+        let elapsed = run_with(&mut interpreter, source).unwrap();
+        assert_eq!(Literal::Number(5.0), elapsed);
+        assert_eq!(
+            Literal::Number(1_700_000_000_010.0),
+            run_with(&mut interpreter, "millis() - 5;").unwrap()
+        );
+        assert_eq!(
+            Literal::Number(1_700_000_000.02),
+            run_with(&mut interpreter, "clock();").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_string_literals_are_shared_not_copied() {
+        let tokens = Scanner::new("\"a long string literal\"".to_string())
+            .scan_tokens()
+            .unwrap();
+        let expr = Parser::new(tokens).parse_expression().unwrap();
+        let mut interpreter = Interpreter::new();
+        match (
+            interpreter.evaluate(&expr).unwrap(),
+            interpreter.evaluate(&expr).unwrap(),
+        ) {
+            (Literal::String(first), Literal::String(second)) => {
+                assert!(Rc::ptr_eq(&first, &second));
+            }
+            other => panic!("expected two strings, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_modulo_takes_the_sign_of_the_dividend() {
+        for (source, expected) in [
+            ("7 % 3;", 1.0),
+            ("-7 % 3;", -1.0),
+            ("7 % -3;", 1.0),
+            ("-7 % -3;", -1.0),
+            ("5.5 % 2;", 1.5),
+            ("1 + 7 % 4 * 2;", 7.0),
+        ] {
+            assert_eq!(
+                Literal::Number(expected),
+                run(source).unwrap(),
+                "{}",
+                source
+            );
+        }
+        let err = run("\"a\" % 2;").unwrap_err();
+        assert_eq!(
+            LoxErrorType::RuntimeError(DetailedErrorType::ExpectedNumber("string")),
+            err.kind
+        );
+        let err = run("1 % 0;").unwrap_err();
+        assert_eq!(
+            LoxErrorType::RuntimeError(DetailedErrorType::DivisionByZero),
+            err.kind
+        );
+    }
+
+    #[test]
+    fn test_natives_lists_native_functions() {
+        let source = "fun clockwork() {} var names = natives(); names;";
+        let names = match run(source).unwrap() {
+            Literal::Array(names) => names.borrow().clone(),
+            other => panic!("expected an array, got {}", other),
+        };
+        assert!(names.contains(&Literal::string("clock")));
+        assert!(names.contains(&Literal::string("natives")));
+        assert!(!names.contains(&Literal::string("clockwork")));
+
+        let mut bare = Interpreter::bare();
+        assert!(bare.native_names().is_empty());
+        bare.define_native_fn("only", 0, |_, _| Ok(Literal::Nil));
+        assert_eq!(vec!["only".to_string()], bare.native_names());
+    }
+
+    #[test]
+    fn test_int_truncates_toward_zero() {
+        for (source, expected) in [
+            ("int(2.7);", 2.0),
+            ("int(-2.7);", -2.0),
+            ("int(\"42.9\");", 42.0),
+            ("int(\" -3.5 \");", -3.0),
+        ] {
+            assert_eq!(
+                Literal::Number(expected),
+                run(source).unwrap(),
+                "{}",
+                source
+            );
+        }
+        for (source, message) in [
+            ("int(\"abc\");", "Cannot convert \"abc\" to a number."),
+            ("int(nil);", "int expects a number or a string."),
+        ] {
+            let err = run(source).unwrap_err();
+            assert_eq!(
+                LoxErrorType::RuntimeError(DetailedErrorType::InvalidArgument(message.to_string())),
+                err.kind
+            );
+        }
+    }
+
+    #[test]
+    fn test_seeded_random_is_deterministic() {
+        let source =
+            "var a = random(); var b = randomInt(10, 20); var c = randomInt(-3, 3); \"${a} ${b} ${c}\";";
+        let first = run_with(&mut Interpreter::with_seed(7), source).unwrap();
+        let second = run_with(&mut Interpreter::with_seed(7), source).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(Literal::string("0.38983 10 2"), first);
+
+        for source in [
+            "randomInt(5, 5);",
+            "randomInt(1.5, 3);",
+            "randomInt(\"a\", 3);",
+        ] {
+            let err = run(source).unwrap_err();
+            assert_eq!(
+                LoxErrorType::RuntimeError(DetailedErrorType::InvalidArgument(
+                    "randomInt expects whole numbers lo < hi.".to_string()
+                )),
+                err.kind,
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn test_power() {
+        for (source, expected) in [
+            ("2 ** 10;", 1024.0),
+            ("2 ** 3 ** 2;", 512.0),
+            ("4 ** 0.5;", 2.0),
+            ("2 ** -1;", 0.5),
+        ] {
+            assert_eq!(
+                Literal::Number(expected),
+                run(source).unwrap(),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn test_seed_restarts_the_sequence() {
+        let source = "
+            seed(99);
+            var first = \"${random()} ${randomInt(0, 1000)}\";
+            seed(99);
+            var second = \"${random()} ${randomInt(0, 1000)}\";
+            first == second;";
+        assert_eq!(Literal::Boolean(true), run(source).unwrap());
+        assert_eq!(
+            run_with(&mut Interpreter::with_seed(99), "random();").unwrap(),
+            run("seed(99); random();").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_function_declaration_defines_a_callable() {
+        match run("fun greet(name) {} greet;").unwrap() {
+            Literal::Function(function) => assert_eq!(1, function.arity()),
+            other => panic!("expected a function, got {}", other),
+        }
+        match run("fun collect(a, b = 1, ...rest) {} collect;").unwrap() {
+            Literal::Function(function) => {
+                assert_eq!(2, function.arity());
+                assert_eq!(Arity::AtLeast(1), function.expected_arity());
+            }
+            other => panic!("expected a function, got {}", other),
+        }
+        assert_eq!(Literal::Nil, run("fun nothing() {} nothing();").unwrap());
+    }
+
+    #[test]
+    fn test_closure_keeps_its_captured_counter() {
+        let source = "
+            fun makeCounter() {
+                var count = 0;
+                fun increment() {
+                    count = count + 1;
+                    return count;
+                }
+                return increment;
+            }
+            var a = makeCounter();
+            var b = makeCounter();
+            a();
+            a();
+            b();
+            a() * 10 + b();";
+        assert_eq!(Literal::Number(32.0), run(source).unwrap());
+    }
+
+    #[test]
+    fn test_assert_and_summary() {
+        let mut interpreter = Interpreter::new();
+        let summary = run_with(
+            &mut interpreter,
+            "assert(true); assert(1 < 2, \"ordering\"); testSummary();",
+        )
+        .unwrap();
+        assert_eq!(Literal::string("2 of 2 assertions passed."), summary);
+
+        let err = run_with(&mut interpreter, "assert(1 > 2, \"one is bigger\");").unwrap_err();
+        assert_eq!("Assertion failed: one is bigger\n[line 1]", err.to_string());
+        let err = run_with(&mut interpreter, "assert(nil);").unwrap_err();
+        assert_eq!("Assertion failed.\n[line 1]", err.to_string());
+        assert_eq!(
+            AssertionCounts { run: 4, passed: 2 },
+            interpreter.assertions
+        );
+    }
+
+    #[test]
+    fn test_calling_a_class_makes_an_instance() {
+        let source = "class Point { norm() { return 0; } } var p = Point(); \"${Point} ${p}\";";
+        assert_eq!(
+            Literal::string("Point Point instance"),
+            run(source).unwrap()
+        );
+        let err = run("class Point {} Point(1);").unwrap_err();
+        assert_eq!(
+            LoxErrorType::RuntimeError(DetailedErrorType::InvalidArity(Arity::Exactly(0), 1)),
+            err.kind
+        );
+    }
+
+    #[test]
+    fn test_functions_and_instances_compare_by_identity() {
+        let source = "
+            fun f() {}
+            fun g() {}
+            var alias = f;
+            class Point {}
+            var p = Point();
+            var q = p;
+            \"${f == alias} ${f == g} ${clock == clock} ${p == q} ${p == Point()} ${Point == Point}\";";
+        assert_eq!(
+            Literal::string("true false true true false true"),
+            run(source).unwrap()
+        );
+
+        let source = "
+            fun list(...items) { return items; }
+            fun f() {}
+            class Point {}
+            var p = Point();
+            list(list(f, p), f, p, Point());";
+        let values = match run(source).unwrap() {
+            Literal::Array(values) => values.borrow().clone(),
+            other => panic!("expected an array, got {}", other),
+        };
+        let (members, f, p, other) = (&values[0], &values[1], &values[2], &values[3]);
+        let members = match members {
+            Literal::Array(members) => members.borrow().clone(),
+            other => panic!("expected an array, got {}", other),
+        };
+        assert!(members.contains(f));
+        assert!(members.contains(p));
+        assert!(!members.contains(other));
+    }
+
+    #[test]
+    fn test_redeclared_function_is_a_new_value() {
+        let source = "
+            fun make() { fun inner() {} return inner; }
+            make() == make();";
+        assert_eq!(Literal::Boolean(false), run(source).unwrap());
+    }
+
+    #[test]
+    fn test_get_property() {
+        let mut interpreter = Interpreter::new();
+        run_with(
+            &mut interpreter,
+            "class Point { origin() { return 0; } } var p = Point();",
+        )
+        .unwrap();
+        match interpreter.globals.borrow().fetch("p") {
+            Some(Literal::Instance(p)) => {
+                p.borrow_mut()
+                    .fields
+                    .insert("x".to_string(), Literal::Number(3.0));
+            }
+            other => panic!("expected an instance, got {:?}", other),
+        }
+        assert_eq!(
+            Literal::Number(3.0),
+            run_with(&mut interpreter, "p.x;").unwrap()
+        );
+        assert_eq!(
+            Literal::Number(0.0),
+            run_with(&mut interpreter, "p.origin();").unwrap()
+        );
+
+        let err = run_with(&mut interpreter, "p.y;").unwrap_err();
+        assert_eq!("Undefined property 'y'.\n[line 1]", err.to_string());
+        let err = run_with(&mut interpreter, "Point.x;").unwrap_err();
+        assert_eq!(
+            LoxErrorType::RuntimeError(DetailedErrorType::OnlyInstancesHaveProperties),
+            err.kind
+        );
+    }
+
+    #[test]
+    fn test_frozen_instance_fields_are_read_only() {
+        let mut interpreter = Interpreter::new();
+        run_with(&mut interpreter, "class Point {} var p = Point();").unwrap();
+        match interpreter.globals.borrow().fetch("p") {
+            Some(Literal::Instance(p)) => {
+                p.borrow_mut()
+                    .fields
+                    .insert("x".to_string(), Literal::Number(3.0));
+            }
+            other => panic!("expected an instance, got {:?}", other),
+        }
+        assert_eq!(
+            Literal::Number(3.0),
+            run_with(&mut interpreter, "freeze(p).x;").unwrap()
+        );
+        let err = run_with(&mut interpreter, "p.x = 4;").unwrap_err();
+        assert_eq!(
+            LoxErrorType::RuntimeError(DetailedErrorType::FrozenInstance),
+            err.kind
+        );
+        let err = run_with(&mut interpreter, "freeze(1);").unwrap_err();
+        assert_eq!(
+            LoxErrorType::RuntimeError(DetailedErrorType::InvalidArgument(
+                "freeze expects an instance.".to_string()
+            )),
+            err.kind
+        );
+    }
+
+    #[test]
+    fn test_set_property() {
+        let source = "
+            class Box {}
+            var b = Box();
+            var first = b.value = 1;
+            b.value = b.value + 10;
+            b.label = \"box\";
+            \"${first} ${b.value} ${b.label}\";";
+        assert_eq!(Literal::string("1 11 box"), run(source).unwrap());
+    }
+
+    #[test]
+    fn test_fluent_chains() {
+        let source = "
+            class Node {}
+            var root = Node();
+            root.next = Node();
+            root.next.value = 5;
+            fun identity(node) { return node; }
+            fun getRoot() { return root; }
+            root.find = identity;
+            root.next.self = getRoot;
+            \"${root.find(root.next).value} ${root.next.self().next.value} ${getRoot().next.self().find(1)}\";";
+        assert_eq!(Literal::string("5 5 1"), run(source).unwrap());
+    }
+
+    #[test]
+    fn test_non_finite_results_print_like_the_reference() {
+        let source = "\"${(-1) ** 0.5} ${10 ** 400} ${-(10 ** 400)}\";";
+        assert_eq!(Literal::string("nan inf -inf"), run(source).unwrap());
+    }
+
+    #[test]
+    fn test_inheritance_and_super() {
+        let source = "
+            class Animal {
+                name() { return \"animal\"; }
+                describe() { return \"an \" + this.name(); }
+            }
+            class Dog < Animal {
+                name() { return \"dog, a kind of \" + super.name(); }
+            }
+            var d = Dog();
+            d.describe();";
+        assert_eq!(
+            Literal::string("an dog, a kind of animal"),
+            run(source).unwrap()
+        );
+
+        let err = run("var NotAClass = 1; class B < NotAClass {}").unwrap_err();
+        assert_eq!(
+            LoxErrorType::RuntimeError(DetailedErrorType::SuperclassMustBeAClass),
+            err.kind
+        );
+    }
+
+    #[test]
+    fn test_methods_see_this() {
+        let source = "
+            class Counter {
+                bump() { this.count = this.count + 1; return this; }
+            }
+            var c = Counter();
+            c.count = 0;
+            c.bump().bump().bump().count;";
+        assert_eq!(Literal::Number(3.0), run(source).unwrap());
+    }
+
+    #[test]
+    fn test_break_leaves_the_innermost_loop() {
+        let source = "
+            var i = 0;
+            while (true) {
+                if (i == 3) break;
+                i = i + 1;
+            }
+            i;";
+        assert_eq!(Literal::Number(3.0), run(source).unwrap());
+
+        let source = "
+            var total = 0;
+            for (var i = 0; i < 3; i = i + 1) {
+                for (var j = 0; j < 10; j = j + 1) {
+                    if (j == 2) break;
+                    total = total + 1;
+                }
+            }
+            total;";
+        assert_eq!(Literal::Number(6.0), run(source).unwrap());
+    }
+
+    #[test]
+    fn test_continue_skips_to_the_next_iteration() {
+        let source = "
+            var sum = 0;
+            for (var i = 0; i < 10; i = i + 1) {
+                if (i % 2 == 1) continue;
+                sum = sum + i;
+            }
+            sum;";
+        assert_eq!(Literal::Number(20.0), run(source).unwrap());
+
+        let source = "
+            var i = 0;
+            var odd = 0;
+            while (i < 5) {
+                i = i + 1;
+                if (i % 2 == 0) continue;
+                odd = odd + 1;
+            }
+            odd;";
+        assert_eq!(Literal::Number(3.0), run(source).unwrap());
+    }
+
+    #[test]
+    fn test_logical_not_negates_truthiness() {
+        let cases = [
+            ("!true", false),
+            ("!false", true),
+            ("!nil", true),
+            ("!0", false),
+            ("!\"\"", false),
+            ("!!\"x\"", true),
+            ("!true == false", true),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(
+                Literal::Boolean(expected),
+                run(&format!("{};", source)).unwrap(),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn test_division_by_zero_is_an_error() {
+        for source in ["1 / 0;", "0 / 0;", "-1 / -0;", "5 % 0;"] {
+            let err = run(source).unwrap_err();
+            assert_eq!(
+                LoxErrorType::RuntimeError(DetailedErrorType::DivisionByZero),
+                err.kind,
+                "{}",
+                source
+            );
+        }
+        assert_eq!(Literal::Number(2.5), run("5 / 2;").unwrap());
+        assert_eq!(Literal::Number(0.0), run("0 / 5;").unwrap());
+    }
+
+    #[test]
+    fn test_prelude_is_loaded_unless_bare() {
+        assert_eq!(Literal::Number(3.0), run("abs(-3);").unwrap());
+        assert_eq!(Literal::Number(10.0), run("clamp(12, 0, 10);").unwrap());
+        assert_eq!(
+            Literal::string("[1, 2]"),
+            run("\"${list(1, 2)}\";").unwrap()
+        );
+
+        let mut bare = Interpreter::bare();
+        let program = crate::compile("abs(-3);").unwrap();
+        let err = bare.interpret(&program).unwrap_err();
+        assert_eq!(
+            LoxErrorType::RuntimeError(DetailedErrorType::UndeclaredIdentifier("abs".to_string())),
+            err.kind
+        );
+    }
+
+    #[test]
+    fn test_string_comparison() {
+        let cases = [
+            ("\"apple\" < \"banana\"", true),
+            ("\"apple\" > \"banana\"", false),
+            ("\"app\" < \"apple\"", true),
+            ("\"Zebra\" < \"apple\"", true),
+            ("\"same\" <= \"same\"", true),
+            ("\"same\" >= \"same\"", true),
+            ("\"same\" < \"same\"", false),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(
+                Literal::Boolean(expected),
+                run(&format!("{};", source)).unwrap(),
+                "{}",
+                source
+            );
+        }
+        let err = run("\"1\" < 2;").unwrap_err();
+        assert_eq!(
+            LoxErrorType::RuntimeError(DetailedErrorType::ExpectedNumber("string")),
+            err.kind
+        );
+    }
+
+    #[test]
+    fn test_blocks_without_declarations_share_the_enclosing_environment() {
+        let mut interpreter = Interpreter::new();
+        // Whether each `mark()` ran directly in the global environment.
+        let marks = Rc::new(RefCell::new(Vec::new()));
+        let seen = Rc::clone(&marks);
+        interpreter.define_native_fn("mark", 0, move |interpreter, _| {
+            seen.borrow_mut()
+                .push(Rc::ptr_eq(&interpreter.environment, &interpreter.globals));
+            Ok(Literal::Nil)
+        });
+
+        let source = "var n = 0; while (n < 2) { mark(); { mark(); n = n + 1; } }";
+        run_with(&mut interpreter, source).unwrap();
+        assert_eq!(vec![true; 4], *marks.borrow());
+
+        marks.borrow_mut().clear();
+        let source = "var x = \"outer\"; { var x = \"inner\"; mark(); } mark(); x;";
+        assert_eq!(
+            Literal::string("outer"),
+            run_with(&mut interpreter, source).unwrap()
+        );
+        assert_eq!(vec![false, true], *marks.borrow());
+    }
+
+    #[test]
+    fn test_plus_type_mismatch() {
+        let cases = [
+            ("\"count: \" + 5;", "string", "number"),
+            ("5 + \"\";", "number", "string"),
+            ("nil + \"x\";", "nil", "string"),
+            ("true + 1;", "boolean", "number"),
+            ("1 + true;", "number", "boolean"),
+        ];
+        for (source, left, right) in cases {
+            let err = run(source).unwrap_err();
+            assert_eq!(
+                LoxErrorType::RuntimeError(DetailedErrorType::OperandsMustBeAddable(left, right)),
+                err.kind,
+                "{}",
+                source
+            );
+        }
+        let err = run("1 + true;").unwrap_err();
+        assert_eq!(
+            "Operands must be two numbers, strings, arrays or maps, got number and boolean.\n[line 1]",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn test_for_with_continue_and_break() {
+        let source = "
+            var iterations = 0;
+            for (var i = 0; i < 100; i = i + 1) {
+                iterations = iterations + 1;
+                if (i % 3 == 0) continue;
+                if (i == 10) break;
+            }
+            iterations;";
+        assert_eq!(Literal::Number(11.0), run(source).unwrap());
+
+        let source = "
+            var i = 0;
+            var seen = 0;
+            for (; i < 5; i = i + 1) {
+                if (i == 1) continue;
+                seen = seen + 1;
+            }
+            \"${i} ${seen}\";";
+        assert_eq!(Literal::string("5 4"), run(source).unwrap());
+
+        let source = "
+            var n = 0;
+            for (;;) {
+                n = n + 1;
+                if (n == 7) break;
+            }
+            n;";
+        assert_eq!(Literal::Number(7.0), run(source).unwrap());
+    }
+
+    #[test]
+    fn test_for_initializer_is_scoped_to_the_loop() {
+        let source = "
+            var i = \"outer\";
+            for (var i = 0; i < 3; i = i + 1) {}
+            i;";
+        assert_eq!(Literal::string("outer"), run(source).unwrap());
+    }
+
+    #[test]
+    fn test_string_repetition() {
+        let cases = [
+            ("\"x\" * 0;", ""),
+            ("\"ab\" * 3;", "ababab"),
+            ("2 * \"ho\";", "hoho"),
+            ("\"\" * 5;", ""),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(
+                Literal::string(expected),
+                run(source).unwrap(),
+                "{}",
+                source
+            );
+        }
+        for source in ["\"y\" * -1;", "\"y\" * 1.5;", "\"y\" * (0 - 1 / 3);"] {
+            let err = run(source).unwrap_err();
+            assert_eq!(
+                LoxErrorType::RuntimeError(DetailedErrorType::InvalidRepetitionCount),
+                err.kind,
+                "{}",
+                source
+            );
+        }
+
+        let mut interpreter = Interpreter::new();
+        interpreter.max_string_length = Some(10);
+        let err = run_with(&mut interpreter, "\"abc\" * 4;").unwrap_err();
+        assert_eq!(
+            LoxErrorType::RuntimeError(DetailedErrorType::StringTooLong),
+            err.kind
+        );
+    }
+
+    #[test]
+    fn test_interpolation_respects_max_string_length() {
+        let mut interpreter = Interpreter::new();
+        interpreter.max_string_length = Some(8);
+        run_with(&mut interpreter, "var s = \"abcd\";").unwrap();
+        assert_eq!(
+            Literal::string("<abcd>"),
+            run_with(&mut interpreter, "\"<${s}>\";").unwrap()
+        );
+
+        let err = run_with(&mut interpreter, "\n\"${s}-${s}\";").unwrap_err();
+        assert_eq!(
+            LoxErrorType::RuntimeError(DetailedErrorType::StringTooLong),
+            err.kind
+        );
+        assert_eq!(2, err.line);
+        assert!(matches!(err.token.token_type, TokenType::Interpolation(_)));
+    }
+
+    #[test]
+    fn test_nan_map_key_is_rejected() {
+        let source = "var m = parseJSON(\"{}\");\nmapSet(m, (-1) ** 0.5, 1);";
+        let err = run(source).unwrap_err();
+        assert_eq!(
+            LoxErrorType::RuntimeError(DetailedErrorType::NanKey),
+            err.kind
+        );
+        assert_eq!(2, err.line);
+
+        let source = "var m = parseJSON(\"{}\"); mapSet(m, 10 ** 400, 1); mapGet(m, 10 ** 400);";
+        assert_eq!(Literal::Number(1.0), run(source).unwrap());
+    }
+
+    #[test]
+    fn test_ternary() {
+        let cases = [
+            ("true ? 1 : 2;", 1.0),
+            ("nil ? 1 : 2;", 2.0),
+            ("0 ? 1 : 2;", 1.0),
+            ("false ? 1 : false ? 2 : 3;", 3.0),
+            ("false ? 1 : true ? 2 : 3;", 2.0),
+            ("true ? false ? 1 : 2 : 3;", 2.0),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(
+                Literal::Number(expected),
+                run(source).unwrap(),
+                "{}",
+                source
+            );
+        }
+        // Only the chosen branch is evaluated.
+        let source = "var hits = 0; fun hit() { hits = hits + 1; } true ? 1 : hit(); hits;";
+        assert_eq!(Literal::Number(0.0), run(source).unwrap());
+    }
+
+    #[test]
+    fn test_expected_number_names_the_actual_type() {
+        let cases = [
+            ("-\"a\";", "string"),
+            ("1 - true;", "boolean"),
+            ("nil < 2;", "nil"),
+            ("2 * clock;", "function"),
+        ];
+        for (source, actual) in cases {
+            let err = run(source).unwrap_err();
+            assert_eq!(
+                LoxErrorType::RuntimeError(DetailedErrorType::ExpectedNumber(actual)),
+                err.kind,
+                "{}",
+                source
+            );
+        }
+        assert_eq!(
+            "Operand must be a number, got nil.\n[line 1]",
+            run("nil < 2;").unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_anonymous_functions() {
+        let source = "var increment = fun (x) { return x + 1; }; increment(41);";
+        assert_eq!(Literal::Number(42.0), run(source).unwrap());
+
+        let source = "
+            fun twice(f, x) { return f(f(x)); }
+            twice(fun (n) { return n * 3; }, 2);";
+        assert_eq!(Literal::Number(18.0), run(source).unwrap());
+
+        let source = "
+            fun counter() {
+                var count = 0;
+                return fun () { count = count + 1; return count; };
+            }
+            var next = counter();
+            next();
+            next();";
+        assert_eq!(Literal::Number(2.0), run(source).unwrap());
+    }
+
+    #[test]
+    fn test_array_concatenation() {
+        let source = "
+            var a = list(1, 2);
+            var b = list(3);
+            var c = a + b;
+            \"${c} ${a} ${b}\";";
+        assert_eq!(
+            Literal::string("[1, 2, 3] [1, 2] [3]"),
+            run(source).unwrap()
+        );
+
+        for source in ["list(1) + 2;", "\"a\" + list(1);", "list() + nil;"] {
+            let err = run(source).unwrap_err();
+            assert_eq!(
+                LoxErrorType::RuntimeError(DetailedErrorType::OperandsMustBeTwoArrays),
+                err.kind,
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn test_resolved_locals_pick_the_right_scope() {
+        let source = "
+            var x = \"global\";
+            fun f() {
+                var x = \"outer\";
+                {
+                    var x = \"inner\";
+                    x = x + \"!\";
+                    return x;
+                }
+            }
+            f() + \" \" + x;";
+        assert_eq!(Literal::string("inner! global"), run(source).unwrap());
+
+        // The interpreter trusts the map: pointing the inner read one scope
+        // further out makes it see the outer binding.
+        let tokens = Scanner::new("{ var x = 1; { var x = 2; see(x); } }".to_string())
+            .scan_tokens()
+            .unwrap();
+        let statements = Parser::new(tokens).parse().unwrap();
+        let locals = crate::resolver::resolve_program(&statements).unwrap();
+        let x = |(token, _): &(Token, Resolution)| token.lexeme == "x";
+        let read: Vec<_> = locals.into_iter().filter(x).collect();
+        assert_eq!(Resolution::Local(0), read[0].1);
+        let moved = read
+            .into_iter()
+            .map(|(token, _)| (token, Resolution::Local(1)))
+            .collect();
+
+        let mut interpreter = Interpreter::new();
+        let seen = Rc::new(RefCell::new(Literal::Nil));
+        let sink = Rc::clone(&seen);
+        interpreter.define_native_fn("see", 1, move |_, args| {
+            *sink.borrow_mut() = args[0].clone();
+            Ok(Literal::Nil)
+        });
+        interpreter.resolve(moved);
+        interpreter.interpret(&statements).unwrap();
+        assert_eq!(Literal::Number(1.0), *seen.borrow());
+    }
+
+    #[test]
+    fn test_map_merge() {
+        let source = "
+            var a = parseJSON(\"{\\\"a\\\": 1, \\\"shared\\\": 1}\");
+            var b = parseJSON(\"{\\\"b\\\": 2, \\\"shared\\\": 2}\");
+            var c = parseJSON(\"{\\\"c\\\": 3}\");
+            \"${a + b} ${a + c} ${b + a} ${a} ${b}\";";
+        assert_eq!(
+            Literal::string(concat!(
+                r#"{"a": 1, "b": 2, "shared": 2} "#,
+                r#"{"a": 1, "c": 3, "shared": 1} "#,
+                r#"{"a": 1, "b": 2, "shared": 1} "#,
+                r#"{"a": 1, "shared": 1} "#,
+                r#"{"b": 2, "shared": 2}"#
+            )),
+            run(source).unwrap()
+        );
+
+        let err = run("parseJSON(\"{}\") + 1;").unwrap_err();
+        assert_eq!(
+            LoxErrorType::RuntimeError(DetailedErrorType::OperandsMustBeTwoMaps),
+            err.kind
+        );
+    }
+
+    /// Source whose resolved read of a local `name` ends exactly where
+    /// `target` does, on the same line.
+    fn local_read_ending_at(target: &Token, name: &str) -> String {
+        let read = format!("{{ var {0} = 0; fun g() {{ return {0}", name);
+        let mut source = "\n".repeat(target.line - 1);
+        let column = target.offset - source.len() - read.len();
+        source.push_str(&" ".repeat(column));
+        source.push_str(&read);
+        source.push_str("; } }");
+        return source;
+    }
+
+    #[test]
+    fn test_resolutions_from_later_programs_do_not_leak() {
+        let mut interpreter = Interpreter::new();
+        let line = format!("fun f(p) {{{}return p; }}", " ".repeat(40));
+        run_with(&mut interpreter, &line).unwrap();
+        let tokens = Scanner::new(line).scan_tokens().unwrap();
+        let read = tokens.iter().filter(|t| t.lexeme == "p").nth(1).unwrap();
+
+        run_with(&mut interpreter, &local_read_ending_at(read, "p")).unwrap();
+        run_with(&mut interpreter, "var p = \"global\";").unwrap();
+        assert_eq!(
+            Literal::string("param"),
+            run_with(&mut interpreter, "f(\"param\");").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_resolutions_do_not_leak_into_the_prelude() {
+        let tokens = Scanner::new(PRELUDE.to_string()).scan_tokens().unwrap();
+        let read = tokens
+            .iter()
+            .rfind(|t| t.lexeme == "n" && t.line == 7)
+            .unwrap();
+        let source = local_read_ending_at(read, "n") + " var n = -1; abs(5);";
+        assert_eq!(Literal::Number(5.0), run(&source).unwrap());
     }
 }
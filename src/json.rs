@@ -0,0 +1,346 @@
+//! JSON conversion for the `toJSON` and `parseJSON` natives. The format is
+//! small enough that a hand-written parser keeps the interpreter free of
+//! dependencies; nothing here needs serde's generality.
+
+use std::collections::HashMap;
+
+use crate::literal::{Literal, MapKey};
+
+/// Serializes a value to a compact JSON document. Map keys are written in
+/// sorted order so the output is deterministic. Functions, and numbers JSON
+/// cannot represent (NaN and the infinities), are rejected.
+pub fn stringify(value: &Literal) -> Result<String, String> {
+    let mut out = String::new();
+    write_value(value, &mut out)?;
+    Ok(out)
+}
+
+fn write_value(value: &Literal, out: &mut String) -> Result<(), String> {
+    match value {
+        Literal::Nil => out.push_str("null"),
+        Literal::Boolean(b) => out.push_str(if *b { "true" } else { "false" }),
+        Literal::Number(n) => {
+            if !n.is_finite() {
+                return Err(format!("Cannot serialize {} to JSON.", n));
+            }
+            out.push_str(&n.to_string());
+        }
+        Literal::String(s) => write_string(s, out),
+        Literal::Array(items) => {
+            out.push('[');
+            for (i, item) in items.borrow().iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_value(item, out)?;
+            }
+            out.push(']');
+        }
+        Literal::Map(entries) => {
+            let entries = entries.borrow();
+            let mut sorted = Vec::new();
+            for (key, value) in entries.iter() {
+                match key {
+                    MapKey::String(key) => sorted.push((key, value)),
+                    _ => return Err("JSON object keys must be strings.".to_string()),
+                }
+            }
+            sorted.sort_by(|a, b| a.0.cmp(b.0));
+            out.push('{');
+            for (i, (key, value)) in sorted.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_string(key, out);
+                out.push(':');
+                write_value(value, out)?;
+            }
+            out.push('}');
+        }
+        Literal::Function(_) => return Err("Cannot serialize a function to JSON.".to_string()),
+        Literal::Class(_) | Literal::Instance(_) => {
+            return Err("Cannot serialize a class or instance to JSON.".to_string())
+        }
+    }
+    Ok(())
+}
+
+fn write_string(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Parses a JSON document into Lox values: objects become maps, arrays
+/// become arrays and all numbers become `Literal::Number`. Errors report
+/// the character offset at which parsing failed.
+pub fn parse(source: &str) -> Result<Literal, String> {
+    let mut parser = JsonParser {
+        chars: source.chars().collect(),
+        current: 0,
+    };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    if parser.current < parser.chars.len() {
+        return Err(parser.error("Unexpected trailing characters"));
+    }
+    Ok(value)
+}
+
+struct JsonParser {
+    chars: Vec<char>,
+    current: usize,
+}
+
+impl JsonParser {
+    fn error(&self, msg: &str) -> String {
+        format!("Invalid JSON at position {}: {}.", self.current, msg)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.current).cloned()
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.current += 1;
+        c
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(' ' | '\t' | '\n' | '\r') = self.peek() {
+            self.current += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.peek() == Some(expected) {
+            self.current += 1;
+            return Ok(());
+        }
+        Err(self.error(&format!("Expected '{}'", expected)))
+    }
+
+    fn value(&mut self) -> Result<Literal, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => self.object(),
+            Some('[') => self.array(),
+            Some('"') => Ok(Literal::string(self.string()?)),
+            Some('t') => self.keyword("true", Literal::Boolean(true)),
+            Some('f') => self.keyword("false", Literal::Boolean(false)),
+            Some('n') => self.keyword("null", Literal::Nil),
+            Some('-' | '0'..='9') => self.number(),
+            Some(_) => Err(self.error("Unexpected character")),
+            None => Err(self.error("Unexpected end of input")),
+        }
+    }
+
+    fn keyword(&mut self, word: &str, value: Literal) -> Result<Literal, String> {
+        for expected in word.chars() {
+            if self.peek() != Some(expected) {
+                return Err(self.error(&format!("Expected '{}'", word)));
+            }
+            self.current += 1;
+        }
+        Ok(value)
+    }
+
+    fn number(&mut self) -> Result<Literal, String> {
+        let start = self.current;
+        while let Some('-' | '+' | '.' | 'e' | 'E' | '0'..='9') = self.peek() {
+            self.current += 1;
+        }
+        let lexeme: String = self.chars[start..self.current].iter().collect();
+        match lexeme.parse() {
+            Ok(n) => Ok(Literal::Number(n)),
+            Err(_) => {
+                self.current = start;
+                Err(self.error("Invalid number"))
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.current += 1;
+        let mut value = String::new();
+        loop {
+            match self.advance() {
+                None => return Err(self.error("Unterminated string")),
+                Some('"') => return Ok(value),
+                Some('\\') => match self.advance() {
+                    Some('"') => value.push('"'),
+                    Some('\\') => value.push('\\'),
+                    Some('/') => value.push('/'),
+                    Some('b') => value.push('\u{8}'),
+                    Some('f') => value.push('\u{c}'),
+                    Some('n') => value.push('\n'),
+                    Some('r') => value.push('\r'),
+                    Some('t') => value.push('\t'),
+                    Some('u') => value.push(self.unicode_escape()?),
+                    _ => return Err(self.error("Invalid escape sequence")),
+                },
+                Some(c) => value.push(c),
+            }
+        }
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let digits: String = self.chars.iter().skip(self.current).take(4).collect();
+        if digits.len() != 4 {
+            return Err(self.error("Invalid unicode escape"));
+        }
+        let code =
+            u32::from_str_radix(&digits, 16).map_err(|_| self.error("Invalid unicode escape"))?;
+        self.current += 4;
+        Ok(code)
+    }
+
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        let mut code = self.hex4()?;
+        // Astral characters are written as a UTF-16 surrogate pair.
+        if (0xD800..0xDC00).contains(&code) {
+            if self.advance() != Some('\\') || self.advance() != Some('u') {
+                return Err(self.error("Expected low surrogate"));
+            }
+            let low = self.hex4()?;
+            if !(0xDC00..0xE000).contains(&low) {
+                return Err(self.error("Invalid low surrogate"));
+            }
+            code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+        }
+        char::from_u32(code).ok_or_else(|| self.error("Invalid unicode escape"))
+    }
+
+    fn array(&mut self) -> Result<Literal, String> {
+        self.current += 1;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.current += 1;
+            return Ok(Literal::array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.advance() {
+                Some(',') => (),
+                Some(']') => return Ok(Literal::array(items)),
+                _ => {
+                    self.current -= 1;
+                    return Err(self.error("Expected ',' or ']'"));
+                }
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Literal, String> {
+        self.current += 1;
+        let mut entries = HashMap::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.current += 1;
+            return Ok(Literal::map(entries));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('"') {
+                return Err(self.error("Expected string key"));
+            }
+            let key = self.string()?;
+            self.expect(':')?;
+            entries.insert(MapKey::String(key.into()), self.value()?);
+            self.skip_whitespace();
+            match self.advance() {
+                Some(',') => (),
+                Some('}') => return Ok(Literal::map(entries)),
+                _ => {
+                    self.current -= 1;
+                    return Err(self.error("Expected ',' or '}'"));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stringify_scalars() {
+        assert_eq!("null", stringify(&Literal::Nil).unwrap());
+        assert_eq!("true", stringify(&Literal::Boolean(true)).unwrap());
+        assert_eq!("42", stringify(&Literal::Number(42.0)).unwrap());
+        assert_eq!("-0.5", stringify(&Literal::Number(-0.5)).unwrap());
+        assert!(stringify(&Literal::Number(f64::NAN)).is_err());
+    }
+
+    #[test]
+    fn test_stringify_escapes_strings() {
+        let value = Literal::string("say \"hi\"\n\\\u{1}");
+        assert_eq!(r#""say \"hi\"\n\\\u0001""#, stringify(&value).unwrap());
+    }
+
+    #[test]
+    fn test_stringify_nested() {
+        let inner = Literal::array(vec![Literal::Number(1.0), Literal::Nil]);
+        let mut entries = HashMap::new();
+        entries.insert(MapKey::String("b".into()), inner);
+        entries.insert(MapKey::String("a".into()), Literal::string("x"));
+        let value = Literal::array(vec![Literal::map(entries), Literal::Boolean(false)]);
+        assert_eq!(
+            r#"[{"a":"x","b":[1,null]},false]"#,
+            stringify(&value).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_scalars() {
+        assert_eq!(Literal::Nil, parse("null").unwrap());
+        assert_eq!(Literal::Boolean(false), parse(" false ").unwrap());
+        assert_eq!(Literal::Number(-12.5e2), parse("-12.5e2").unwrap());
+        assert_eq!(
+            Literal::string("a\"b\n\u{e9}\u{1F600}"),
+            parse(r#""a\"b\n\u00e9\ud83d\ude00""#).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_collections() {
+        let value = parse(r#"{"list": [1, [2], {}], "empty": []}"#).unwrap();
+        assert_eq!(r#"{"empty": [], "list": [1, [2], {}]}"#, value.to_string());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let source = r#"{"a":[1,{"b":null,"c":"d\"e"}],"f":true}"#;
+        assert_eq!(source, stringify(&parse(source).unwrap()).unwrap());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            "Invalid JSON at position 6: Expected ',' or ']'.",
+            parse("[1, 2 3]").unwrap_err()
+        );
+        assert_eq!(
+            "Invalid JSON at position 5: Unexpected trailing characters.",
+            parse("true false").unwrap_err()
+        );
+        assert!(parse(r#"{"a" 1}"#).is_err());
+        assert!(parse(r#""open"#).is_err());
+        assert!(parse("").is_err());
+    }
+}
@@ -0,0 +1,98 @@
+//! A tree-walking interpreter for Lox. The `lox` binary is a thin wrapper
+//! around these modules; embedders can drive `Interpreter` directly.
+
+#![allow(clippy::needless_return, clippy::result_large_err)]
+
+/// Language features and whether this interpreter supports them yet,
+/// reported by `lox --features`. Flip an entry when its feature lands.
+pub const FEATURES: &[(&str, bool)] = &[
+    ("functions", true),
+    ("closures", true),
+    ("default parameters", true),
+    ("rest parameters", true),
+    ("keyword arguments", true),
+    ("string interpolation", true),
+    ("triple-quoted strings", true),
+    ("bit shifts", true),
+    ("arrays", true),
+    ("maps", true),
+    ("json", true),
+    ("classes", true),
+    ("inheritance", true),
+    ("break", true),
+    ("continue", true),
+    ("prelude", true),
+    ("conditional expressions", true),
+    ("anonymous functions", true),
+];
+
+/// Scans and parses `source` once. The program can then be run any number
+/// of times, by any interpreter, with `Interpreter::run_compiled`. It is
+/// not resolved, so variables are looked up by name.
+pub fn compile(source: &str) -> Result<Program, Vec<errors::LoxError>> {
+    let tokens = scanner::Scanner::new(source.to_string())
+        .scan_tokens()
+        .map_err(|errors| {
+            errors
+                .into_iter()
+                .map(errors::LoxError::from)
+                .collect::<Vec<_>>()
+        })?;
+    let statements = parser::Parser::new(tokens).parse()?;
+    return Ok(Program {
+        statements,
+        locals: std::rc::Rc::default(),
+    });
+}
+
+/// A parsed program bundled with its variable resolution, if any, so it
+/// can be run repeatedly without resolving it again. It derefs to its
+/// statements.
+#[derive(Debug)]
+pub struct Program {
+    pub statements: Vec<stmt::Stmt>,
+    pub locals: std::rc::Rc<resolver::ResolutionMap>,
+}
+
+impl std::ops::Deref for Program {
+    type Target = [stmt::Stmt];
+
+    fn deref(&self) -> &Self::Target {
+        &self.statements
+    }
+}
+
+/// Like `compile`, but also runs the resolver, reporting its errors too.
+/// Warnings are dropped; use `Resolver` directly to see them.
+pub fn compile_resolved(source: &str) -> Result<Program, Vec<errors::LoxError>> {
+    let statements = compile(source)?.statements;
+    let locals = resolver::Resolver::new()
+        .resolve_statements(&statements)
+        .map_err(|errors| {
+            errors
+                .into_iter()
+                .map(errors::LoxError::from)
+                .collect::<Vec<_>>()
+        })?;
+    return Ok(Program {
+        statements,
+        locals: std::rc::Rc::new(locals),
+    });
+}
+
+pub mod class;
+pub mod environment;
+pub mod errors;
+pub mod expr;
+pub mod function;
+pub mod interpreter;
+pub mod json;
+pub mod literal;
+pub mod metrics;
+pub mod natives;
+pub mod parser;
+pub mod random;
+pub mod resolver;
+pub mod scanner;
+pub mod stmt;
+pub mod token;
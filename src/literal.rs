@@ -1,16 +1,31 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::fmt::{Debug, Display};
+use std::rc::Rc;
 
+use crate::class::{Class, Instance};
+use crate::errors::DetailedErrorType;
 use crate::function::Function;
 
 #[derive(Clone)]
 pub enum Literal {
     Function(Function),
-    String(String),
+    Class(Rc<Class>),
+    Instance(Rc<RefCell<Instance>>),
+    Array(Rc<RefCell<Vec<Literal>>>),
+    Map(Rc<RefCell<HashMap<MapKey, Literal>>>),
+    /// Strings are immutable, so values share one buffer: evaluating a
+    /// string literal or copying a string variable is a reference count
+    /// bump, not an allocation.
+    String(Rc<str>),
     Number(f64),
     Boolean(bool),
     Nil,
 }
 
+/// Lox equality is strict: values of different types are never equal, so
+/// `1 == true` and `nil == false` are both false. There is no coercion.
+/// Functions, classes and instances are equal only to themselves.
 impl PartialEq for Literal {
     fn eq(&self, rhs: &Self) -> bool {
         match (self, rhs) {
@@ -18,19 +33,134 @@ impl PartialEq for Literal {
             (Self::Number(lhs), Self::Number(rhs)) => lhs == rhs,
             (Self::Boolean(lhs), Self::Boolean(rhs)) => lhs == rhs,
             (Self::Nil, Self::Nil) => true,
+            (Self::Array(lhs), Self::Array(rhs)) => *lhs.borrow() == *rhs.borrow(),
+            (Self::Map(lhs), Self::Map(rhs)) => *lhs.borrow() == *rhs.borrow(),
+            (Self::Function(lhs), Self::Function(rhs)) => lhs == rhs,
+            (Self::Class(lhs), Self::Class(rhs)) => Rc::ptr_eq(lhs, rhs),
+            (Self::Instance(lhs), Self::Instance(rhs)) => Rc::ptr_eq(lhs, rhs),
             (_, _) => false,
         }
     }
 }
 
+/// A map key, made from one of the hashable values: a string, number,
+/// boolean or nil. Numbers are kept as their bits with `-0` folded into
+/// `0`, since the two compare equal, and NaN is refused, since it is
+/// unequal even to itself. Equal keys are then identical, so `Eq` and
+/// `Hash` hold.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum MapKey {
+    String(Rc<str>),
+    Number(u64),
+    Boolean(bool),
+    Nil,
+}
+
+impl MapKey {
+    /// The key for `value`, or the error for a value that can't be one.
+    pub fn new(value: &Literal) -> Result<Self, DetailedErrorType> {
+        match value {
+            Literal::String(s) => Ok(Self::String(s.clone())),
+            Literal::Number(n) if n.is_nan() => Err(DetailedErrorType::NanKey),
+            Literal::Number(n) if *n == 0.0 => Ok(Self::Number(0.0f64.to_bits())),
+            Literal::Number(n) => Ok(Self::Number(n.to_bits())),
+            Literal::Boolean(b) => Ok(Self::Boolean(*b)),
+            Literal::Nil => Ok(Self::Nil),
+            _ => Err(DetailedErrorType::UnhashableKey),
+        }
+    }
+
+    /// The value the key stands for.
+    pub fn to_literal(&self) -> Literal {
+        match self {
+            Self::String(s) => Literal::String(s.clone()),
+            Self::Number(bits) => Literal::Number(f64::from_bits(*bits)),
+            Self::Boolean(b) => Literal::Boolean(*b),
+            Self::Nil => Literal::Nil,
+        }
+    }
+}
+
+/// Formats a number the way C's `printf("%g")` does, as the reference
+/// implementation prints them: six significant digits, trailing zeros
+/// dropped, and scientific notation when the exponent is below -4 or at
+/// least 6.
+fn format_number(n: f64) -> String {
+    if !n.is_finite() {
+        return match n {
+            n if n.is_nan() => "nan".to_string(),
+            n if n > 0.0 => "inf".to_string(),
+            _ => "-inf".to_string(),
+        };
+    }
+
+    // Rounding to six significant digits first settles the exponent, so
+    // 999999.5 is treated as 1e+06.
+    let scientific = format!("{:.5e}", n);
+    let (mantissa, exponent) = scientific.split_once('e').unwrap();
+    let exponent: i32 = exponent.parse().unwrap();
+
+    if (-4..6).contains(&exponent) {
+        let fixed = format!("{:.*}", (5 - exponent) as usize, n);
+        return strip_fraction_zeros(&fixed).to_string();
+    }
+    let sign = if exponent < 0 { '-' } else { '+' };
+    format!(
+        "{}e{}{:02}",
+        strip_fraction_zeros(mantissa),
+        sign,
+        exponent.abs()
+    )
+}
+
+fn strip_fraction_zeros(digits: &str) -> &str {
+    if !digits.contains('.') {
+        return digits;
+    }
+    digits.trim_end_matches('0').trim_end_matches('.')
+}
+
+/// Writes a string back in Lox source syntax, minus the quotes.
+fn escape(s: &str) -> String {
+    let mut escaped = String::new();
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\r' => escaped.push_str("\\r"),
+            '\0' => escaped.push_str("\\0"),
+            '$' if chars.peek() == Some(&'{') => escaped.push_str("\\$"),
+            '\\' | '"' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            c if c.is_control() => escaped.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    return escaped;
+}
+
+/// Map entries are listed in key order so output doesn't depend on
+/// hashing.
+fn format_entries(entries: &HashMap<MapKey, Literal>) -> String {
+    let mut entries: Vec<_> = entries
+        .iter()
+        .map(|(key, value)| format!("{:?}: {:?}", key.to_literal(), value))
+        .collect();
+    entries.sort();
+    entries.join(", ")
+}
+
 impl Debug for Literal {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::String(s) => {
-                write!(f, "\"{}\"", s)
+                write!(f, "\"{}\"", escape(s))
             }
             Self::Number(n) => {
-                write!(f, "{}", n)
+                write!(f, "{}", format_number(*n))
             }
             Self::Boolean(b) => {
                 write!(f, "{}", b)
@@ -41,6 +171,19 @@ impl Debug for Literal {
             Self::Function(_) => {
                 write!(f, "<native fn>")
             }
+            Self::Class(class) => {
+                write!(f, "{}", class.name)
+            }
+            Self::Instance(instance) => {
+                write!(f, "{} instance", instance.borrow().class.name)
+            }
+            Self::Array(items) => {
+                let items: Vec<_> = items.borrow().iter().map(|i| format!("{:?}", i)).collect();
+                write!(f, "[{}]", items.join(", "))
+            }
+            Self::Map(entries) => {
+                write!(f, "{{{}}}", format_entries(&entries.borrow()))
+            }
         }
     }
 }
@@ -52,7 +195,7 @@ impl Display for Literal {
                 write!(f, "{}", s)
             }
             Self::Number(n) => {
-                write!(f, "{}", n)
+                write!(f, "{}", format_number(*n))
             }
             Self::Boolean(b) => {
                 write!(f, "{}", b)
@@ -63,16 +206,115 @@ impl Display for Literal {
             Self::Function(_) => {
                 write!(f, "<native fn>")
             }
+            Self::Class(class) => {
+                write!(f, "{}", class.name)
+            }
+            Self::Instance(instance) => {
+                write!(f, "{} instance", instance.borrow().class.name)
+            }
+            Self::Array(items) => {
+                let items: Vec<_> = items.borrow().iter().map(|i| format!("{:?}", i)).collect();
+                write!(f, "[{}]", items.join(", "))
+            }
+            Self::Map(entries) => {
+                write!(f, "{{{}}}", format_entries(&entries.borrow()))
+            }
         }
     }
 }
 
 impl Literal {
-    pub fn is_truthy(&self) -> bool {
+    pub fn string(value: impl Into<Rc<str>>) -> Self {
+        Self::String(value.into())
+    }
+
+    pub fn array(items: Vec<Literal>) -> Self {
+        Self::Array(Rc::new(RefCell::new(items)))
+    }
+
+    pub fn map(entries: HashMap<MapKey, Literal>) -> Self {
+        Self::Map(Rc::new(RefCell::new(entries)))
+    }
+
+    /// The name of the value's type, as error messages refer to it.
+    pub fn type_name(&self) -> &'static str {
         match self {
-            Self::Nil => false,
-            Self::Boolean(false) => false,
-            _ => true,
+            Self::Function(_) => "function",
+            Self::Class(_) => "class",
+            Self::Instance(_) => "instance",
+            Self::Array(_) => "array",
+            Self::Map(_) => "map",
+            Self::String(_) => "string",
+            Self::Number(_) => "number",
+            Self::Boolean(_) => "boolean",
+            Self::Nil => "nil",
+        }
+    }
+
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Self::Nil | Self::Boolean(false))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_number_display_matches_printf_g() {
+        let cases = [
+            (0.0, "0"),
+            (-0.0, "-0"),
+            (1.0, "1"),
+            (-2.5, "-2.5"),
+            (0.1 + 0.2, "0.3"),
+            (12.3456789, "12.3457"),
+            (100000.0, "100000"),
+            (999999.0, "999999"),
+            (999999.5, "1e+06"),
+            (1000000.0, "1e+06"),
+            (123456789.0, "1.23457e+08"),
+            (1e21, "1e+21"),
+            (0.0001, "0.0001"),
+            (0.00001234, "1.234e-05"),
+            (1e-7, "1e-07"),
+            (1.5e300, "1.5e+300"),
+            (f64::INFINITY, "inf"),
+            (f64::NEG_INFINITY, "-inf"),
+            (f64::NAN, "nan"),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(expected, Literal::Number(value).to_string(), "{}", value);
         }
     }
+
+    #[test]
+    fn test_integral_numbers_print_without_a_fraction() {
+        let cases = [
+            (100.0, "100"),
+            (10.5, "10.5"),
+            (-0.0, "-0"),
+            (1e21, "1e+21"),
+        ];
+        for (value, expected) in cases {
+            let number = Literal::Number(value);
+            assert_eq!(expected, number.to_string(), "{}", value);
+            assert_eq!(expected, format!("{:?}", number), "{}", value);
+        }
+    }
+
+    #[test]
+    fn test_map_keys_normalise_numbers() {
+        let zero = MapKey::new(&Literal::Number(0.0)).unwrap();
+        assert_eq!(zero, MapKey::new(&Literal::Number(-0.0)).unwrap());
+        assert_eq!(Literal::Number(0.0), zero.to_literal());
+        assert_eq!(
+            Err(DetailedErrorType::NanKey),
+            MapKey::new(&Literal::Number(f64::NAN))
+        );
+        assert_eq!(
+            Err(DetailedErrorType::UnhashableKey),
+            MapKey::new(&Literal::array(vec![]))
+        );
+    }
 }
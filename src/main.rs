@@ -1,23 +1,50 @@
-use interpreter::Interpreter;
-use literal::Literal;
-use parser::Parser;
+#![allow(clippy::needless_return)]
 
-use crate::scanner::Scanner;
+use lox::{
+    interpreter::Interpreter, literal::Literal, parser::Parser, resolver::Resolver,
+    scanner::Scanner,
+};
 use std::{env, fs, io::Write};
 
-mod environment;
-mod errors;
-mod expr;
-mod function;
-mod interpreter;
-mod literal;
-mod parser;
-mod resolver;
-mod scanner;
-mod stmt;
-mod token;
-
-fn run(interpreter: &mut Interpreter, source: String) -> Option<Literal> {
+/// Settings chosen on the command line.
+#[derive(Default)]
+struct Options {
+    /// Promote resolver warnings to errors.
+    strict: bool,
+    /// Log each statement to stderr as it executes.
+    trace: bool,
+    /// Stop at `breakpoint()` calls.
+    debug: bool,
+    /// Open the REPL after the script finishes, with its globals in scope.
+    interactive: bool,
+    /// Printed before each line of REPL input, `> ` if not given.
+    prompt: Option<String>,
+    /// Printed before each value the REPL echoes, `=> ` if not given.
+    result_prefix: Option<String>,
+}
+
+/// Why a run stopped early. The exit codes follow the reference
+/// implementation.
+#[derive(Debug)]
+enum Failure {
+    Compile,
+    Runtime,
+}
+
+impl Failure {
+    fn exit_code(&self) -> i32 {
+        match self {
+            Self::Compile => 65,
+            Self::Runtime => 70,
+        }
+    }
+}
+
+fn run(
+    interpreter: &mut Interpreter,
+    source: String,
+    options: &Options,
+) -> Result<Literal, Failure> {
     let mut scanner = Scanner::new(source);
     let tokens = scanner.scan_tokens();
 
@@ -25,60 +52,126 @@ fn run(interpreter: &mut Interpreter, source: String) -> Option<Literal> {
         Ok(tokens) => {
             let mut parser = Parser::new(tokens);
             let statements = parser.parse();
-            let mut last: Option<Literal> = None;
             match statements {
                 Ok(statements) => {
-                    for stmt in statements {
-                        match interpreter.execute(&stmt) {
-                            Err(reason) => {
-                                println!("{:?}", reason);
-                                break;
-                            }
-                            Ok(result) => {
-                                last = Some(result);
-                            }
+                    let mut resolver = Resolver::new();
+                    let resolved = resolver.resolve_statements(&statements);
+                    for warning in resolver.warnings() {
+                        eprintln!("{}", warning);
+                    }
+                    if let Err(errors) = resolved {
+                        for error in errors {
+                            println!("{}", error);
+                        }
+                        return Err(Failure::Compile);
+                    }
+                    if options.strict && !resolver.warnings().is_empty() {
+                        println!("Aborting: warnings are treated as errors in strict mode.");
+                        return Err(Failure::Compile);
+                    }
+                    if let Ok(locals) = resolved {
+                        interpreter.resolve(locals);
+                    }
+                    match interpreter.interpret(&statements) {
+                        Ok(result) => {
+                            return Ok(result);
+                        }
+                        Err(reason) => {
+                            println!("{}", reason);
+                            return Err(Failure::Runtime);
                         }
                     }
-                    return last;
                 }
-                Err(reason) => {
-                    println!("{:?}", reason);
-                    return None;
+                Err(errors) => {
+                    for error in errors {
+                        println!("{}", error);
+                    }
+                    return Err(Failure::Compile);
                 }
             }
         }
         Err(errors) => {
             for error in errors {
-                println!("{:?}", error);
+                println!("{}", error);
             }
-            return None;
+            return Err(Failure::Compile);
         }
     }
 }
 
-fn run_file(filename: String) {
-    let contents = fs::read_to_string(filename).unwrap();
+/// Runs one line of REPL input and returns the value to echo, if any. A
+/// bare expression with no trailing `;` is evaluated and echoed; anything
+/// else runs as statements and echoes nothing.
+fn run_repl_line(
+    interpreter: &mut Interpreter,
+    line: &str,
+    options: &Options,
+) -> Result<Option<Literal>, Failure> {
+    if line.trim().is_empty() {
+        return Ok(None);
+    }
+    if let Ok(tokens) = Scanner::new(line.to_string()).scan_tokens() {
+        if let Ok(expr) = Parser::new(tokens).parse_expression() {
+            return match interpreter.evaluate(&expr) {
+                Ok(value) => Ok(Some(value)),
+                Err(reason) => {
+                    println!("{}", reason);
+                    Err(Failure::Runtime)
+                }
+            };
+        }
+    }
+    run(interpreter, line.to_string(), options)?;
+    Ok(None)
+}
+
+/// An interpreter set up as the command line asks, with the capabilities a
+/// local user expects.
+fn new_interpreter(options: &Options) -> Interpreter {
     let mut interpreter = Interpreter::new();
-    run(&mut interpreter, contents);
+    interpreter.trace = options.trace;
+    interpreter.debug = options.debug;
+    interpreter.allow_filesystem = true;
+    interpreter.allow_environment = true;
+    return interpreter;
+}
+
+/// Runs scripts in order in one interpreter, so later ones see what
+/// earlier ones defined, and hands the interpreter back, globals and all.
+/// `args` are the command-line arguments after the scripts, which they see
+/// as the global `args` array. Exits the process at the first script that
+/// fails.
+fn run_files(filenames: Vec<String>, args: Vec<String>, options: &Options) -> Interpreter {
+    let mut interpreter = new_interpreter(options);
+    let args = args.into_iter().map(Literal::string).collect();
+    interpreter
+        .globals
+        .borrow_mut()
+        .define("args", Literal::array(args));
+    for filename in filenames {
+        let contents = fs::read_to_string(filename).unwrap();
+        if let Err(failure) = run(&mut interpreter, contents, options) {
+            std::process::exit(failure.exit_code());
+        }
+    }
+    return interpreter;
 }
 
-fn run_prompt() {
+fn run_prompt(mut interpreter: Interpreter, options: &Options) {
     let mut buffer = String::new();
-    let mut interpreter = Interpreter::new();
+    let prompt = options.prompt.as_deref().unwrap_or("> ");
+    let result_prefix = options.result_prefix.as_deref().unwrap_or("=> ");
 
     loop {
-        print!("> ");
+        print!("{}", prompt);
         std::io::stdout().flush().unwrap();
         match std::io::stdin().read_line(&mut buffer) {
             Ok(0) => {
                 break;
             }
             Ok(_) => {
-                match run(&mut interpreter, buffer.clone()) {
-                    Some(value) => {
-                        println!("=> {}", value);
-                    }
-                    _ => (),
+                if let Ok(Some(value)) = run_repl_line(&mut interpreter, &buffer, options) {
+                    println!("{}{}", result_prefix, value);
                 }
                 buffer.clear();
             }
@@ -89,14 +182,112 @@ fn run_prompt() {
     }
 }
 
+fn usage() -> ! {
+    println!("Usage: lox [--version | --features] [--strict | -W error] [--trace] [--debug] [-i] [--prompt text] [--result-prefix text] [--load file.lox]... [script.lox [--] [args...]]");
+    std::process::exit(64);
+}
+
 fn main() {
-    if env::args().len() > 2 {
-        println!("Usage: lox [script]");
-        std::process::exit(64);
-    } else if env::args().len() == 2 {
-        let args: Vec<_> = env::args().collect();
-        run_file(args[1].clone());
-    } else {
-        run_prompt();
+    let mut options = Options::default();
+    // Files given with `--load`, in order, then the script itself.
+    let mut scripts = Vec::new();
+    let mut script = None;
+    let mut script_args = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--strict" => options.strict = true,
+            "--trace" => options.trace = true,
+            "--debug" => options.debug = true,
+            "-i" | "--interactive-after-file" => options.interactive = true,
+            "--version" => {
+                println!("lox {}", env!("CARGO_PKG_VERSION"));
+                return;
+            }
+            "--features" => {
+                for (feature, supported) in lox::FEATURES {
+                    println!("{}: {}", feature, if *supported { "yes" } else { "no" });
+                }
+                return;
+            }
+            "--prompt" => match args.next() {
+                Some(prompt) => options.prompt = Some(prompt),
+                None => usage(),
+            },
+            "--result-prefix" => match args.next() {
+                Some(prefix) => options.result_prefix = Some(prefix),
+                None => usage(),
+            },
+            "-W" => match args.next().as_deref() {
+                Some("error") => options.strict = true,
+                _ => usage(),
+            },
+            "--load" => match args.next() {
+                Some(file) => scripts.push(file),
+                None => usage(),
+            },
+            _ if arg.starts_with('-') => usage(),
+            _ => {
+                script = Some(arg);
+                // Everything after the script is its arguments, `.lox` files
+                // included. A `--` right after it is dropped.
+                let mut rest = args.by_ref().peekable();
+                rest.next_if(|arg| arg == "--");
+                script_args.extend(rest);
+            }
+        }
+    }
+
+    // Without a script, the REPL starts once the loaded files have run.
+    let interactive = options.interactive || script.is_none();
+    scripts.extend(script);
+    if scripts.is_empty() {
+        return run_prompt(new_interpreter(&options), &options);
+    }
+    let interpreter = run_files(scripts, script_args, &options);
+    if interactive {
+        run_prompt(interpreter, &options);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repl_echoes_bare_expressions_only() {
+        let mut interpreter = Interpreter::new();
+        let options = Options::default();
+        let mut line = |source: &str| run_repl_line(&mut interpreter, source, &options).unwrap();
+
+        assert_eq!(Some(Literal::Number(2.0)), line("1 + 1\n"));
+        assert_eq!(None, line("var x = 1;\n"));
+        assert_eq!(Some(Literal::Number(1.0)), line("x"));
+        assert_eq!(None, line("x = 3;"));
+        assert_eq!(Some(Literal::Number(3.0)), line("x"));
+        assert_eq!(None, line("\n"));
+    }
+
+    #[test]
+    fn test_repl_does_not_echo_loops_or_blocks() {
+        let mut interpreter = Interpreter::new();
+        let options = Options::default();
+        let mut line = |source: &str| run_repl_line(&mut interpreter, source, &options).unwrap();
+
+        assert_eq!(None, line("var i = 0; while (i < 3) i = i + 1;"));
+        assert_eq!(None, line("{ i; }"));
+        assert_eq!(Some(Literal::Nil), line("nil"));
+    }
+
+    #[test]
+    fn test_repl_reports_errors_inside_anonymous_functions() {
+        let mut interpreter = Interpreter::new();
+        let options = Options::default();
+
+        let result = run_repl_line(&mut interpreter, "fun () { print ; }()", &options);
+        assert!(matches!(result, Err(Failure::Compile)));
+        let result = run_repl_line(&mut interpreter, "fun () { var; print 1; }", &options);
+        assert!(matches!(result, Err(Failure::Compile)));
     }
 }
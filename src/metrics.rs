@@ -0,0 +1,247 @@
+//! Size measurements over the AST, for tools that report on program
+//! complexity or want to put limits on it.
+
+use crate::{
+    expr::{Expr, ExprVisitor},
+    literal::Literal,
+    stmt::{Parameter, Stmt, StmtVisitor},
+    token::Token,
+};
+
+/// The number of statements and expressions in a program.
+pub fn count_nodes(stmts: &[Stmt]) -> usize {
+    stmts
+        .iter()
+        .map(|stmt| stmt.accept(&mut Measure).nodes)
+        .sum()
+}
+
+/// The deepest chain of nested statements and expressions in a program: 2
+/// for `print x;`, 3 for `print -x;`. An empty program has depth 0.
+pub fn max_depth(stmts: &[Stmt]) -> usize {
+    stmts
+        .iter()
+        .map(|stmt| stmt.accept(&mut Measure).depth)
+        .max()
+        .unwrap_or(0)
+}
+
+#[derive(Clone, Copy, Default)]
+struct Size {
+    nodes: usize,
+    depth: usize,
+}
+
+/// The size of a node with the given children.
+fn node(children: impl IntoIterator<Item = Size>) -> Size {
+    let mut size = Size { nodes: 1, depth: 0 };
+    for child in children {
+        size.nodes += child.nodes;
+        size.depth = size.depth.max(child.depth);
+    }
+    size.depth += 1;
+    return size;
+}
+
+struct Measure;
+
+impl Measure {
+    fn all_stmts(&mut self, stmts: &[Stmt]) -> Vec<Size> {
+        stmts.iter().map(|stmt| stmt.accept(self)).collect()
+    }
+
+    fn all_exprs<'a>(&mut self, exprs: impl IntoIterator<Item = &'a Expr>) -> Vec<Size> {
+        exprs.into_iter().map(|expr| expr.accept(self)).collect()
+    }
+}
+
+impl StmtVisitor<Size> for Measure {
+    fn visit_print_stmt(&mut self, expr: &Expr) -> Size {
+        node([expr.accept(self)])
+    }
+
+    fn visit_expression_stmt(&mut self, expr: &Expr) -> Size {
+        node([expr.accept(self)])
+    }
+
+    fn visit_var_stmt(&mut self, _name: &Token, initializer: Option<&Expr>) -> Size {
+        node(self.all_exprs(initializer))
+    }
+
+    fn visit_block_stmt(&mut self, statements: &[Stmt], _line: usize) -> Size {
+        node(self.all_stmts(statements))
+    }
+
+    fn visit_if_stmt(
+        &mut self,
+        condition: &Expr,
+        then_branch: &Stmt,
+        else_branch: Option<&Stmt>,
+    ) -> Size {
+        let mut children = vec![condition.accept(self), then_branch.accept(self)];
+        children.extend(else_branch.map(|stmt| stmt.accept(self)));
+        node(children)
+    }
+
+    fn visit_while_stmt(&mut self, condition: &Expr, body: &Stmt) -> Size {
+        node([condition.accept(self), body.accept(self)])
+    }
+
+    fn visit_for_stmt(
+        &mut self,
+        initializer: Option<&Stmt>,
+        condition: &Expr,
+        increment: Option<&Expr>,
+        body: &Stmt,
+    ) -> Size {
+        let mut children: Vec<Size> = initializer.into_iter().map(|s| s.accept(self)).collect();
+        children.push(condition.accept(self));
+        children.extend(self.all_exprs(increment));
+        children.push(body.accept(self));
+        node(children)
+    }
+
+    fn visit_function_stmt(&mut self, _name: &Token, params: &[Parameter], body: &[Stmt]) -> Size {
+        let mut children = self.all_exprs(params.iter().filter_map(|p| p.default.as_ref()));
+        children.extend(self.all_stmts(body));
+        node(children)
+    }
+
+    fn visit_return_stmt(&mut self, _keyword: &Token, value: Option<&Expr>) -> Size {
+        node(self.all_exprs(value))
+    }
+
+    fn visit_break_stmt(&mut self, _keyword: &Token) -> Size {
+        node([])
+    }
+
+    fn visit_continue_stmt(&mut self, _keyword: &Token) -> Size {
+        node([])
+    }
+
+    fn visit_class_stmt(
+        &mut self,
+        _name: &Token,
+        superclass: Option<&Expr>,
+        methods: &[Stmt],
+    ) -> Size {
+        let mut children = self.all_exprs(superclass);
+        children.extend(self.all_stmts(methods));
+        node(children)
+    }
+}
+
+impl ExprVisitor<Size> for Measure {
+    fn visit_assign_expr(&mut self, _name: &Token, value: &Expr) -> Size {
+        node([value.accept(self)])
+    }
+
+    fn visit_binary_expr(&mut self, left: &Expr, _operator: &Token, right: &Expr) -> Size {
+        node([left.accept(self), right.accept(self)])
+    }
+
+    fn visit_call_expr(
+        &mut self,
+        callee: &Expr,
+        _paren: &Token,
+        arguments: &[Expr],
+        keywords: &[(Token, Expr)],
+    ) -> Size {
+        let mut children = vec![callee.accept(self)];
+        children.extend(self.all_exprs(arguments));
+        children.extend(self.all_exprs(keywords.iter().map(|(_, arg)| arg)));
+        node(children)
+    }
+
+    fn visit_function_expr(
+        &mut self,
+        _keyword: &Token,
+        params: &[Parameter],
+        body: &[Stmt],
+    ) -> Size {
+        let mut children = self.all_exprs(params.iter().filter_map(|p| p.default.as_ref()));
+        children.extend(self.all_stmts(body));
+        node(children)
+    }
+
+    fn visit_get_expr(&mut self, object: &Expr, _name: &Token) -> Size {
+        node([object.accept(self)])
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &Expr, _line: usize) -> Size {
+        node([expr.accept(self)])
+    }
+
+    fn visit_interpolation_expr(&mut self, parts: &[Expr], _start: &Token) -> Size {
+        node(self.all_exprs(parts))
+    }
+
+    fn visit_literal_expr(&mut self, _value: &Literal, _line: usize) -> Size {
+        node([])
+    }
+
+    fn visit_logical_expr(&mut self, left: &Expr, _operator: &Token, right: &Expr) -> Size {
+        node([left.accept(self), right.accept(self)])
+    }
+
+    fn visit_ternary_expr(
+        &mut self,
+        condition: &Expr,
+        then_branch: &Expr,
+        else_branch: &Expr,
+    ) -> Size {
+        node([
+            condition.accept(self),
+            then_branch.accept(self),
+            else_branch.accept(self),
+        ])
+    }
+
+    fn visit_set_expr(&mut self, object: &Expr, _name: &Token, value: &Expr) -> Size {
+        node([object.accept(self), value.accept(self)])
+    }
+
+    fn visit_super_expr(&mut self, _keyword: &Token, _method: &Token) -> Size {
+        node([])
+    }
+
+    fn visit_this_expr(&mut self, _keyword: &Token) -> Size {
+        node([])
+    }
+
+    fn visit_unary_expr(&mut self, _operator: &Token, right: &Expr) -> Size {
+        node([right.accept(self)])
+    }
+
+    fn visit_var_expr(&mut self, _name: &Token) -> Size {
+        node([])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, scanner::Scanner};
+
+    fn parse(source: &str) -> Vec<Stmt> {
+        let tokens = Scanner::new(source.to_string()).scan_tokens().unwrap();
+        Parser::new(tokens).parse().unwrap()
+    }
+
+    #[test]
+    fn test_count_nodes_and_max_depth() {
+        let cases = [
+            ("print x;", 2, 2),
+            ("print -x; var y;", 4, 3),
+            ("if (a) { print 1 + 2; }", 7, 5),
+            ("fun f(n = 1) { return n; }", 4, 3),
+            ("f(1, key: 2);", 5, 3),
+        ];
+        for (source, nodes, depth) in cases {
+            let program = parse(source);
+            assert_eq!(nodes, count_nodes(&program), "{}", source);
+            assert_eq!(depth, max_depth(&program), "{}", source);
+        }
+        assert_eq!(0, max_depth(&[]));
+    }
+}
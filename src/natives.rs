@@ -0,0 +1,482 @@
+use std::{
+    fs,
+    rc::Rc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    environment::Environment,
+    errors::DetailedErrorType,
+    function::{Arity, Function},
+    interpreter::Interpreter,
+    json,
+    literal::{Literal, MapKey},
+    parser::Parser,
+    random::Rng,
+    scanner::Scanner,
+};
+
+pub type NativeResult = Result<Literal, DetailedErrorType>;
+
+fn define_native(
+    env: &mut Environment,
+    name: &str,
+    arity: impl Into<Arity>,
+    body: fn(&mut Interpreter, &[Literal]) -> NativeResult,
+) {
+    env.define(
+        name,
+        Literal::Function(Function::Native {
+            arity: arity.into(),
+            body: Rc::new(body),
+        }),
+    );
+}
+
+pub fn define_natives(env: &mut Environment) {
+    define_native(env, "clock", 0, clock);
+    define_native(env, "millis", 0, millis);
+    define_native(env, "toJSON", 1, to_json);
+    define_native(env, "parseJSON", 1, parse_json);
+    define_native(env, "formatNumber", 2, format_number);
+    define_native(env, "callDepth", 0, call_depth);
+    define_native(env, "breakpoint", 0, breakpoint);
+    define_native(env, "printDebug", 1, print_debug);
+    define_native(env, "natives", 0, natives);
+    define_native(env, "minOf", 1, min_of);
+    define_native(env, "maxOf", 1, max_of);
+    define_native(env, "zip", 2, zip);
+    define_native(env, "mapGet", 2, map_get);
+    define_native(env, "mapSet", 3, map_set);
+    define_native(env, "readFile", 1, read_file);
+    define_native(env, "writeFile", 2, write_file);
+    define_native(env, "env", 1, env_var);
+    define_native(env, "partial", Arity::AtLeast(1), partial);
+    define_native(env, "compose", 2, compose);
+    define_native(env, "identity", 1, identity);
+    define_native(env, "constant", 1, constant);
+    define_native(env, "int", 1, int);
+    define_native(env, "random", 0, random);
+    define_native(env, "randomInt", 2, random_int);
+    define_native(env, "seed", 1, seed);
+    define_native(env, "assert", Arity::Between(1, 2), assert);
+    define_native(env, "testSummary", 0, test_summary);
+    define_native(env, "freeze", 1, freeze);
+}
+
+/// The interpreter's default clock: the system time since the Unix epoch.
+pub fn system_clock() -> Duration {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap()
+}
+
+/// The interpreter's default environment: the process's variables. Unset
+/// and non-Unicode variables both read as `None`.
+pub fn process_env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+}
+
+/// The interpreter's default input: the next line of stdin. A read error
+/// ends input just like end of file.
+pub fn stdin_line() -> Option<String> {
+    let mut line = String::new();
+    match std::io::stdin().read_line(&mut line) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(line),
+    }
+}
+
+/// Fractional seconds since the epoch.
+fn clock(interpreter: &mut Interpreter, _args: &[Literal]) -> NativeResult {
+    Ok(Literal::Number((interpreter.clock)().as_secs_f64()))
+}
+
+/// Whole milliseconds since the epoch. Today's counts are around 2^41, well
+/// within the 2^53 an f64 holds exactly, so these stay exact integers for
+/// the next few hundred thousand years.
+fn millis(interpreter: &mut Interpreter, _args: &[Literal]) -> NativeResult {
+    Ok(Literal::Number((interpreter.clock)().as_millis() as f64))
+}
+
+/// A float in `[0, 1)`.
+fn random(interpreter: &mut Interpreter, _args: &[Literal]) -> NativeResult {
+    Ok(Literal::Number(interpreter.rng.next_f64()))
+}
+
+/// A whole number in `[lo, hi)`.
+fn random_int(interpreter: &mut Interpreter, args: &[Literal]) -> NativeResult {
+    match (&args[0], &args[1]) {
+        (Literal::Number(lo), Literal::Number(hi))
+            if lo.fract() == 0.0 && hi.fract() == 0.0 && lo < hi =>
+        {
+            let offset = (interpreter.rng.next_f64() * (hi - lo)).floor();
+            Ok(Literal::Number(lo + offset))
+        }
+        _ => Err(DetailedErrorType::InvalidArgument(
+            "randomInt expects whole numbers lo < hi.".to_string(),
+        )),
+    }
+}
+
+/// Reseeds the generator, so the `random()` and `randomInt()` calls after it
+/// repeat the same sequence each time the same seed is given.
+fn seed(interpreter: &mut Interpreter, args: &[Literal]) -> NativeResult {
+    match &args[0] {
+        Literal::Number(n) if n.fract() == 0.0 => {
+            interpreter.rng = Rng::from_seed(*n as i64 as u64);
+            Ok(Literal::Nil)
+        }
+        _ => Err(DetailedErrorType::InvalidArgument(
+            "seed expects a whole number.".to_string(),
+        )),
+    }
+}
+
+/// Fails with a runtime error, naming the optional message, unless
+/// `args[0]` is truthy.
+fn assert(interpreter: &mut Interpreter, args: &[Literal]) -> NativeResult {
+    interpreter.assertions.run += 1;
+    if args[0].is_truthy() {
+        interpreter.assertions.passed += 1;
+        return Ok(Literal::Nil);
+    }
+    Err(DetailedErrorType::AssertionFailed(
+        args.get(1).map(|message| message.to_string()),
+    ))
+}
+
+fn test_summary(interpreter: &mut Interpreter, _args: &[Literal]) -> NativeResult {
+    let counts = interpreter.assertions;
+    Ok(Literal::string(format!(
+        "{} of {} assertions passed.",
+        counts.passed, counts.run
+    )))
+}
+
+/// Makes an instance's fields read-only from then on, and returns it.
+fn freeze(_interpreter: &mut Interpreter, args: &[Literal]) -> NativeResult {
+    match &args[0] {
+        Literal::Instance(instance) => {
+            instance.borrow_mut().frozen = true;
+            Ok(args[0].clone())
+        }
+        _ => Err(DetailedErrorType::InvalidArgument(
+            "freeze expects an instance.".to_string(),
+        )),
+    }
+}
+
+fn to_json(_interpreter: &mut Interpreter, args: &[Literal]) -> NativeResult {
+    json::stringify(&args[0])
+        .map(Literal::string)
+        .map_err(DetailedErrorType::InvalidArgument)
+}
+
+fn parse_json(_interpreter: &mut Interpreter, args: &[Literal]) -> NativeResult {
+    match &args[0] {
+        Literal::String(source) => json::parse(source).map_err(DetailedErrorType::InvalidArgument),
+        _ => Err(DetailedErrorType::InvalidArgument(
+            "parseJSON expects a string.".to_string(),
+        )),
+    }
+}
+
+/// The most fractional digits `formatNumber` will produce.
+const MAX_DECIMALS: f64 = 100.0;
+
+/// Formats a number with a fixed count of fractional digits, rounding the
+/// last one.
+fn format_number(_interpreter: &mut Interpreter, args: &[Literal]) -> NativeResult {
+    let value = match &args[0] {
+        Literal::Number(value) => *value,
+        _ => {
+            return Err(DetailedErrorType::InvalidArgument(
+                "formatNumber expects a number.".to_string(),
+            ))
+        }
+    };
+    match &args[1] {
+        Literal::Number(decimals)
+            if decimals.fract() == 0.0 && (0.0..=MAX_DECIMALS).contains(decimals) =>
+        {
+            Ok(Literal::string(format!("{:.*}", *decimals as usize, value)))
+        }
+        _ => Err(DetailedErrorType::InvalidArgument(
+            "formatNumber expects a whole number of decimals between 0 and 100.".to_string(),
+        )),
+    }
+}
+
+/// See `Interpreter::call_depth` for how frames are counted.
+fn call_depth(interpreter: &mut Interpreter, _args: &[Literal]) -> NativeResult {
+    Ok(Literal::Number(interpreter.call_depth() as f64))
+}
+
+/// Prints a value the way it would be written in source: strings come out
+/// quoted, with escapes.
+fn print_debug(interpreter: &mut Interpreter, args: &[Literal]) -> NativeResult {
+    interpreter.write_line(&format!("{:?}", args[0]));
+    Ok(Literal::Nil)
+}
+
+fn natives(interpreter: &mut Interpreter, _args: &[Literal]) -> NativeResult {
+    let names = interpreter.native_names();
+    Ok(Literal::array(
+        names.into_iter().map(Literal::string).collect(),
+    ))
+}
+
+/// With debugging enabled, reads lines with `Interpreter::read_line` and
+/// runs them in the caller's scope until the user types `continue` (or
+/// input ends). A missing trailing semicolon is added, so bare expressions
+/// can be inspected. Without `--debug` this does nothing.
+fn breakpoint(interpreter: &mut Interpreter, _args: &[Literal]) -> NativeResult {
+    if !interpreter.debug {
+        return Ok(Literal::Nil);
+    }
+
+    loop {
+        interpreter.write_prompt("debug> ");
+        let line = match (interpreter.read_line)() {
+            None => break,
+            Some(line) if line.trim() == "continue" => break,
+            Some(line) => line,
+        };
+
+        let mut source = line.trim().to_string();
+        if !source.ends_with(';') && !source.ends_with('}') {
+            source.push(';');
+        }
+        let tokens = match Scanner::new(source).scan_tokens() {
+            Ok(tokens) => tokens,
+            Err(errors) => {
+                errors
+                    .iter()
+                    .for_each(|error| interpreter.write_line(&error.to_string()));
+                continue;
+            }
+        };
+        let statements = match Parser::new(tokens).parse() {
+            Ok(statements) => statements,
+            Err(errors) => {
+                errors
+                    .iter()
+                    .for_each(|error| interpreter.write_line(&error.to_string()));
+                continue;
+            }
+        };
+        match interpreter.interpret(&statements) {
+            Ok(value) => interpreter.write_line(&format!("=> {}", value)),
+            Err(error) => interpreter.write_line(&error.to_string()),
+        }
+    }
+    Ok(Literal::Nil)
+}
+
+/// Folds an array of numbers down to one with `pick`. A NaN anywhere in
+/// the array makes the result NaN, whatever its position.
+fn reduce_numbers(name: &str, array: &Literal, pick: fn(f64, f64) -> f64) -> NativeResult {
+    let items = match array {
+        Literal::Array(items) => items.borrow(),
+        _ => {
+            return Err(DetailedErrorType::InvalidArgument(format!(
+                "{} expects an array.",
+                name
+            )))
+        }
+    };
+    let mut result: Option<f64> = None;
+    for item in items.iter() {
+        let value = match item {
+            Literal::Number(value) => *value,
+            _ => {
+                return Err(DetailedErrorType::InvalidArgument(format!(
+                    "{} expects an array of numbers.",
+                    name
+                )))
+            }
+        };
+        result = Some(match result {
+            None => value,
+            Some(current) if current.is_nan() || value.is_nan() => f64::NAN,
+            Some(current) => pick(current, value),
+        });
+    }
+    result.map(Literal::Number).ok_or_else(|| {
+        DetailedErrorType::InvalidArgument(format!("{} expects a non-empty array.", name))
+    })
+}
+
+fn min_of(_interpreter: &mut Interpreter, args: &[Literal]) -> NativeResult {
+    reduce_numbers("minOf", &args[0], f64::min)
+}
+
+fn max_of(_interpreter: &mut Interpreter, args: &[Literal]) -> NativeResult {
+    reduce_numbers("maxOf", &args[0], f64::max)
+}
+
+/// Pairs up the elements of two arrays as `[a[i], b[i]]`. The result is as
+/// long as the shorter array; the rest of the longer one is ignored.
+fn zip(_interpreter: &mut Interpreter, args: &[Literal]) -> NativeResult {
+    match (&args[0], &args[1]) {
+        (Literal::Array(left), Literal::Array(right)) => {
+            let pairs = left
+                .borrow()
+                .iter()
+                .zip(right.borrow().iter())
+                .map(|(a, b)| Literal::array(vec![a.clone(), b.clone()]))
+                .collect();
+            Ok(Literal::array(pairs))
+        }
+        _ => Err(DetailedErrorType::InvalidArgument(
+            "zip expects two arrays.".to_string(),
+        )),
+    }
+}
+
+/// Looks up `key` in a map, returning nil when it is absent.
+fn map_get(_interpreter: &mut Interpreter, args: &[Literal]) -> NativeResult {
+    match &args[0] {
+        Literal::Map(entries) => Ok(MapKey::new(&args[1])
+            .ok()
+            .and_then(|key| entries.borrow().get(&key).cloned())
+            .unwrap_or(Literal::Nil)),
+        _ => Err(DetailedErrorType::InvalidArgument(
+            "mapGet expects a map.".to_string(),
+        )),
+    }
+}
+
+/// Stores `value` under `key` in a map and returns the value. Keys must be
+/// strings, numbers other than NaN, booleans or nil; see `MapKey`.
+fn map_set(_interpreter: &mut Interpreter, args: &[Literal]) -> NativeResult {
+    let entries = match &args[0] {
+        Literal::Map(entries) => entries,
+        _ => {
+            return Err(DetailedErrorType::InvalidArgument(
+                "mapSet expects a map.".to_string(),
+            ))
+        }
+    };
+    let key = MapKey::new(&args[1])?;
+    entries.borrow_mut().insert(key, args[2].clone());
+    Ok(args[2].clone())
+}
+
+/// The string arguments to a filesystem native, once access has been
+/// checked.
+fn filesystem_args<'a>(
+    interpreter: &Interpreter,
+    name: &str,
+    args: &'a [Literal],
+) -> Result<Vec<&'a str>, DetailedErrorType> {
+    if !interpreter.allow_filesystem {
+        return Err(DetailedErrorType::CapabilityDisabled("Filesystem"));
+    }
+    args.iter()
+        .map(|arg| match arg {
+            Literal::String(s) => Ok(&**s),
+            _ => Err(DetailedErrorType::InvalidArgument(format!(
+                "{} expects string arguments.",
+                name
+            ))),
+        })
+        .collect()
+}
+
+fn read_file(interpreter: &mut Interpreter, args: &[Literal]) -> NativeResult {
+    let args = filesystem_args(interpreter, "readFile", args)?;
+    fs::read_to_string(args[0])
+        .map(Literal::string)
+        .map_err(|error| DetailedErrorType::Io(format!("Could not read {}: {}.", args[0], error)))
+}
+
+fn write_file(interpreter: &mut Interpreter, args: &[Literal]) -> NativeResult {
+    let args = filesystem_args(interpreter, "writeFile", args)?;
+    fs::write(args[0], args[1])
+        .map(|_| Literal::Nil)
+        .map_err(|error| DetailedErrorType::Io(format!("Could not write {}: {}.", args[0], error)))
+}
+
+/// The value of an environment variable, or nil if it is unset or not
+/// valid Unicode.
+fn env_var(interpreter: &mut Interpreter, args: &[Literal]) -> NativeResult {
+    if !interpreter.allow_environment {
+        return Err(DetailedErrorType::CapabilityDisabled("Environment"));
+    }
+    match &args[0] {
+        Literal::String(name) => Ok((interpreter.env_var)(name)
+            .map(Literal::string)
+            .unwrap_or(Literal::Nil)),
+        _ => Err(DetailedErrorType::InvalidArgument(
+            "env expects a string.".to_string(),
+        )),
+    }
+}
+
+/// Binds leading arguments to a function. Calling the result prepends them
+/// to the arguments it is given.
+fn partial(_interpreter: &mut Interpreter, args: &[Literal]) -> NativeResult {
+    let inner = match &args[0] {
+        Literal::Function(inner) => inner.clone(),
+        _ => {
+            return Err(DetailedErrorType::InvalidArgument(
+                "partial expects a function.".to_string(),
+            ))
+        }
+    };
+    let bound = args[1..].to_vec();
+    if bound.len() > inner.arity() && !inner.is_variadic() {
+        return Err(DetailedErrorType::InvalidArgument(
+            "partial was given more arguments than the function takes.".to_string(),
+        ));
+    }
+    Ok(Literal::Function(Function::Partial {
+        inner: Box::new(inner),
+        bound,
+    }))
+}
+
+/// Chains two single-argument functions: `compose(f, g)(x)` is `f(g(x))`.
+fn compose(_interpreter: &mut Interpreter, args: &[Literal]) -> NativeResult {
+    match (&args[0], &args[1]) {
+        (Literal::Function(outer), Literal::Function(inner))
+            if outer.accepts(1) && inner.accepts(1) =>
+        {
+            Ok(Literal::Function(Function::Composed {
+                outer: Box::new(outer.clone()),
+                inner: Box::new(inner.clone()),
+            }))
+        }
+        _ => Err(DetailedErrorType::InvalidArgument(
+            "compose expects two single-argument functions.".to_string(),
+        )),
+    }
+}
+
+fn identity(_interpreter: &mut Interpreter, args: &[Literal]) -> NativeResult {
+    Ok(args[0].clone())
+}
+
+/// A function of no arguments that always returns `args[0]`.
+fn constant(_interpreter: &mut Interpreter, args: &[Literal]) -> NativeResult {
+    Ok(Literal::Function(Function::Constant(Box::new(
+        args[0].clone(),
+    ))))
+}
+
+/// Truncates a number, or a string holding one, toward zero.
+fn int(_interpreter: &mut Interpreter, args: &[Literal]) -> NativeResult {
+    match &args[0] {
+        Literal::Number(n) => Ok(Literal::Number(n.trunc())),
+        Literal::String(s) => match s.trim().parse::<f64>() {
+            Ok(n) if n.is_finite() => Ok(Literal::Number(n.trunc())),
+            _ => Err(DetailedErrorType::InvalidArgument(format!(
+                "Cannot convert {:?} to a number.",
+                args[0]
+            ))),
+        },
+        _ => Err(DetailedErrorType::InvalidArgument(
+            "int expects a number or a string.".to_string(),
+        )),
+    }
+}
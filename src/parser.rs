@@ -2,17 +2,26 @@ use crate::{
     errors::LoxError,
     expr::Expr,
     literal::Literal,
-    stmt::Stmt,
+    stmt::{Parameter, Stmt},
     token::{Token, TokenType},
 };
 
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
+    /// Errors recovered from so far, at the top level or inside blocks.
+    errors: Vec<LoxError>,
+    /// Parsing gives up once this many errors have been found.
+    pub max_errors: usize,
+    /// How many loops enclose the current statement, within the innermost
+    /// function. `break` is only allowed when this is non-zero.
+    loop_depth: usize,
 }
 
 type ParseResult<T> = Result<T, LoxError>;
 
+const DEFAULT_MAX_ERRORS: usize = 20;
+
 macro_rules! match_any_token {
     ($parser:expr, $($token:expr),* ) => {
         $(
@@ -23,36 +32,78 @@ macro_rules! match_any_token {
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, current: 0 }
+        Self {
+            tokens,
+            current: 0,
+            errors: Vec::new(),
+            max_errors: DEFAULT_MAX_ERRORS,
+            loop_depth: 0,
+        }
     }
 
     pub fn parse(&mut self) -> Result<Vec<Stmt>, Vec<LoxError>> {
-        if self.tokens.len() == 1 {
-            return Ok(vec![Stmt::Expression(Expr::Literal(Literal::Nil))]);
-        }
-
         let mut program = Vec::new();
-        let mut errors = Vec::new();
         while !self.is_at_end() {
-            match self.declaration() {
-                Ok(stmt) => {
-                    program.push(stmt);
-                }
-                Err(reason) => {
-                    errors.push(reason);
-                    self.synchronize();
-                }
+            if let Some(stmt) = self.recovering_declaration() {
+                program.push(stmt);
             }
         }
-        if errors.is_empty() {
+        if self.errors.is_empty() {
             return Ok(program);
         }
-        return Err(errors);
+        if self.errors.len() >= self.error_cap() {
+            self.errors.truncate(self.error_cap());
+            let last = self.errors.last().unwrap().token.clone();
+            self.errors
+                .push(LoxError::parse_error(&last, "Too many errors, giving up."));
+        }
+        return Err(std::mem::take(&mut self.errors));
+    }
+
+    /// How many errors to collect before giving up. A cap of zero still
+    /// reports the first error rather than none at all.
+    fn error_cap(&self) -> usize {
+        return self.max_errors.max(1);
+    }
+
+    /// Parses a declaration, recording its error instead of returning it.
+    /// `declaration` has already skipped ahead to the next statement, so
+    /// parsing can carry on.
+    fn recovering_declaration(&mut self) -> Option<Stmt> {
+        match self.declaration() {
+            Ok(stmt) => Some(stmt),
+            Err(reason) => {
+                self.errors.push(reason);
+                if self.errors.len() >= self.error_cap() {
+                    // Skip to the end so every enclosing loop stops too.
+                    self.current = self.tokens.len() - 1;
+                }
+                None
+            }
+        }
+    }
+
+    /// Parses the whole token stream as a single expression, with nothing
+    /// after it. Errors inside the bodies of anonymous functions are
+    /// reported too.
+    pub fn parse_expression(&mut self) -> Result<Expr, Vec<LoxError>> {
+        let expr = self.expression().map_err(|reason| vec![reason])?;
+        if !self.is_at_end() {
+            return Err(vec![LoxError::parse_error(
+                &self.peek(),
+                "Expected end of expression.",
+            )]);
+        }
+        if !self.errors.is_empty() {
+            return Err(std::mem::take(&mut self.errors));
+        }
+        return Ok(expr);
     }
 
     fn declaration(&mut self) -> ParseResult<Stmt> {
         let result = match self.peek().token_type {
-            TokenType::Fun => {
+            // `fun (` starts an anonymous function, which is an expression.
+            TokenType::Fun if self.peek_next().token_type != TokenType::LeftParen => {
                 self.advance();
                 self.function_declaration("function")
             }
@@ -60,6 +111,10 @@ impl Parser {
                 self.advance();
                 self.var_declaration()
             }
+            TokenType::Class => {
+                self.advance();
+                self.class_declaration()
+            }
             _ => self.statement(),
         };
 
@@ -72,6 +127,22 @@ impl Parser {
         }
     }
 
+    fn class_declaration(&mut self) -> ParseResult<Stmt> {
+        let name = self.consume_identifier("Expected class name.")?;
+        let mut superclass = None;
+        if self.match_token(&TokenType::Less) {
+            let superclass_name = self.consume_identifier("Expected superclass name.")?;
+            superclass = Some(Expr::Var(superclass_name));
+        }
+        self.consume(&TokenType::LeftBrace, "Expected '{' before class body.")?;
+        let mut methods = Vec::new();
+        while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
+            methods.push(self.function_declaration("method")?);
+        }
+        self.consume(&TokenType::RightBrace, "Expected '}' after class body.")?;
+        Ok(Stmt::Class(name, superclass, methods))
+    }
+
     fn function_declaration(&mut self, kind: impl Into<String>) -> ParseResult<Stmt> {
         let kind = kind.into();
         let name = self.consume_identifier(format!("Expected {} name.", kind).as_str())?;
@@ -79,10 +150,37 @@ impl Parser {
             &TokenType::LeftParen,
             format!("Expected '(' after {} name.", kind).as_str(),
         )?;
+        let (params, body) = self.function_rest(&kind)?;
+        Ok(Stmt::Function(name, params, body))
+    }
+
+    /// Parses a function's parameters and body, from just after the `(`.
+    /// Shared by declarations and anonymous functions.
+    fn function_rest(&mut self, kind: &str) -> ParseResult<(Vec<Parameter>, Vec<Stmt>)> {
         let mut params = Vec::new();
         if !self.check(&TokenType::RightParen) {
             loop {
-                params.push(self.consume_identifier("Expected parameter name.")?);
+                let rest = self.match_token(&TokenType::Ellipsis);
+                let name = self.consume_identifier("Expected parameter name.")?;
+                let default = if !rest && self.match_token(&TokenType::Equal) {
+                    Some(self.expression()?)
+                } else {
+                    None
+                };
+                if default.is_none()
+                    && !rest
+                    && params.iter().any(|p: &Parameter| p.default.is_some())
+                {
+                    return Err(LoxError::parse_error(
+                        &name,
+                        "A parameter without a default cannot follow one with a default.",
+                    ));
+                }
+                params.push(Parameter {
+                    name,
+                    default,
+                    rest,
+                });
                 if params.len() >= 255 {
                     let err = LoxError::parse_error(
                         self.previous(),
@@ -93,6 +191,12 @@ impl Parser {
                 if !self.match_token(&TokenType::Comma) {
                     break;
                 }
+                if rest {
+                    return Err(LoxError::parse_error(
+                        self.previous(),
+                        "A rest parameter must be the last parameter.",
+                    ));
+                }
             }
         }
         self.consume(&TokenType::RightParen, "Expected ')' after parameter list.")?;
@@ -102,9 +206,12 @@ impl Parser {
             format!("Expected '{{' before {} body.", kind).as_str(),
         )?;
 
-        let body = self.parse_block()?;
+        // A loop around the declaration doesn't make `break` valid inside it.
+        let enclosing_loops = std::mem::take(&mut self.loop_depth);
+        let body = self.parse_block();
+        self.loop_depth = enclosing_loops;
 
-        Ok(Stmt::Function(name, params, body))
+        Ok((params, body?))
     }
 
     fn consume_identifier(&mut self, msg: &str) -> ParseResult<Token> {
@@ -156,14 +263,18 @@ impl Parser {
                 self.advance();
                 self.while_statement()
             }
+            TokenType::Break | TokenType::Continue => {
+                self.advance();
+                self.loop_jump_statement()
+            }
             TokenType::For => {
                 self.advance();
                 self.for_statement()
             }
             TokenType::LeftBrace => {
-                self.advance();
+                let line = self.advance().line;
                 let block = self.parse_block()?;
-                Ok(Stmt::Block(block))
+                Ok(Stmt::Block(block, line))
             }
             TokenType::If => {
                 self.advance();
@@ -193,8 +304,9 @@ impl Parser {
         let mut statements = Vec::new();
 
         while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
-            let stmt = self.declaration()?;
-            statements.push(stmt);
+            if let Some(stmt) = self.recovering_declaration() {
+                statements.push(stmt);
+            }
         }
 
         self.consume(&TokenType::RightBrace, "Expected '}' after block.")?;
@@ -218,16 +330,44 @@ impl Parser {
         Ok(Stmt::Return(keyword.clone(), value))
     }
 
+    /// Parses `break` or `continue`, whichever was just consumed.
+    fn loop_jump_statement(&mut self) -> ParseResult<Stmt> {
+        let keyword = self.previous().clone();
+        if self.loop_depth == 0 {
+            return Err(LoxError::parse_error(
+                &keyword,
+                format!("Can't use '{}' outside of a loop.", keyword.lexeme),
+            ));
+        }
+        self.consume(
+            &TokenType::Semicolon,
+            format!("Expected ';' after '{}'.", keyword.lexeme).as_str(),
+        )?;
+        if keyword.token_type == TokenType::Break {
+            return Ok(Stmt::Break(keyword));
+        }
+        Ok(Stmt::Continue(keyword))
+    }
+
+    /// Parses a loop body, counting it as inside a loop for `break`.
+    fn loop_body(&mut self) -> ParseResult<Stmt> {
+        self.loop_depth += 1;
+        let body = self.statement();
+        self.loop_depth -= 1;
+        return body;
+    }
+
     fn while_statement(&mut self) -> ParseResult<Stmt> {
         self.consume(&TokenType::LeftParen, "Expected '(' after 'while'.")?;
         let condition = self.expression()?;
         self.consume(&TokenType::RightParen, "Expected ')' after condition.")?;
-        let body = self.statement()?;
+        let body = self.loop_body()?;
 
         Ok(Stmt::While(condition, Box::new(body)))
     }
 
     fn for_statement(&mut self) -> ParseResult<Stmt> {
+        let line = self.previous().line;
         self.consume(&TokenType::LeftParen, "Expected '(' after 'for'.")?;
         let initializer = if self.match_token(&TokenType::Var) {
             Some(self.var_declaration()?)
@@ -249,26 +389,19 @@ impl Parser {
         };
         self.consume(&TokenType::RightParen, "Expected ')' after for clause.")?;
 
-        let body = self.statement()?;
-
-        let body = match increment {
-            Some(increment) => Stmt::Block(vec![body.clone(), Stmt::Expression(increment)]),
-            None => body,
-        };
+        let body = self.loop_body()?;
 
         let condition = match condition {
             Some(condition) => condition,
-            None => Expr::Literal(Literal::Boolean(true)),
+            None => Expr::Literal(Literal::Boolean(true), line),
         };
 
-        let loop_stmt = Stmt::While(condition, Box::new(body.clone()));
-
-        let result = match initializer {
-            Some(initializer) => Stmt::Block(vec![initializer, loop_stmt]),
-            None => body,
-        };
-
-        Ok(result)
+        Ok(Stmt::For(
+            initializer.map(Box::new),
+            condition,
+            increment,
+            Box::new(body),
+        ))
     }
 
     fn expr_statement(&mut self) -> ParseResult<Stmt> {
@@ -293,7 +426,9 @@ impl Parser {
                 | TokenType::If
                 | TokenType::While
                 | TokenType::Print
-                | TokenType::Return => {
+                | TokenType::Return
+                | TokenType::Break
+                | TokenType::Continue => {
                     return;
                 }
                 _ => (),
@@ -308,7 +443,7 @@ impl Parser {
     }
 
     fn assignment(&mut self) -> ParseResult<Expr> {
-        let expr = self.or();
+        let expr = self.ternary();
 
         if self.match_token(&TokenType::Equal) {
             let value = self.assignment()?;
@@ -317,6 +452,9 @@ impl Parser {
                 Ok(Expr::Var(name)) => {
                     return Ok(Expr::Assign(name, Box::new(value)));
                 }
+                Ok(Expr::Get(object, name)) => {
+                    return Ok(Expr::Set(object, name, Box::new(value)));
+                }
                 _ => {
                     return Err(LoxError::parse_error(
                         self.previous(),
@@ -329,6 +467,26 @@ impl Parser {
         return expr;
     }
 
+    /// `condition ? then : else`, right-associative so that a chain of
+    /// them reads like `if`/`else if`.
+    fn ternary(&mut self) -> ParseResult<Expr> {
+        let condition = self.or()?;
+        if !self.match_token(&TokenType::Question) {
+            return Ok(condition);
+        }
+        let then_branch = self.expression()?;
+        self.consume(
+            &TokenType::Colon,
+            "Expected ':' after then branch of conditional expression.",
+        )?;
+        let else_branch = self.ternary()?;
+        return Ok(Expr::Ternary(
+            Box::new(condition),
+            Box::new(then_branch),
+            Box::new(else_branch),
+        ));
+    }
+
     fn or(&mut self) -> ParseResult<Expr> {
         let mut expr = self.and()?;
 
@@ -366,7 +524,7 @@ impl Parser {
     }
 
     fn comparison(&mut self) -> ParseResult<Expr> {
-        let mut expr = self.term()?;
+        let mut expr = self.shift()?;
 
         while match_any_token!(
             self,
@@ -375,6 +533,18 @@ impl Parser {
             TokenType::Less,
             TokenType::LessEqual
         ) {
+            let operator = self.previous().clone();
+            let right = self.shift()?;
+            expr = Expr::Binary(Box::new(expr), operator.to_owned(), Box::new(right));
+        }
+
+        return Ok(expr);
+    }
+
+    fn shift(&mut self) -> ParseResult<Expr> {
+        let mut expr = self.term()?;
+
+        while match_any_token!(self, TokenType::LessLess, TokenType::GreaterGreater) {
             let operator = self.previous().clone();
             let right = self.term()?;
             expr = Expr::Binary(Box::new(expr), operator.to_owned(), Box::new(right));
@@ -396,17 +566,30 @@ impl Parser {
     }
 
     fn factor(&mut self) -> ParseResult<Expr> {
-        let mut expr = self.unary()?;
+        let mut expr = self.power()?;
 
-        while match_any_token!(self, TokenType::Slash, TokenType::Star) {
+        while match_any_token!(self, TokenType::Slash, TokenType::Star, TokenType::Percent) {
             let operator = self.previous().clone();
-            let right = self.unary()?;
+            let right = self.power()?;
             expr = Expr::Binary(Box::new(expr), operator.to_owned(), Box::new(right));
         }
 
         return Ok(expr);
     }
 
+    /// `**` is right-associative: `2 ** 3 ** 2` is `2 ** (3 ** 2)`.
+    fn power(&mut self) -> ParseResult<Expr> {
+        let expr = self.unary()?;
+
+        if match_any_token!(self, TokenType::StarStar) {
+            let operator = self.previous().clone();
+            let right = self.power()?;
+            return Ok(Expr::Binary(Box::new(expr), operator, Box::new(right)));
+        }
+
+        return Ok(expr);
+    }
+
     fn unary(&mut self) -> ParseResult<Expr> {
         if match_any_token!(self, TokenType::Bang, TokenType::Minus) {
             let operator = self.previous().clone();
@@ -423,6 +606,9 @@ impl Parser {
         loop {
             if self.match_token(&TokenType::LeftParen) {
                 expr = self.finish_call(&expr)?;
+            } else if self.match_token(&TokenType::Dot) {
+                let name = self.consume_identifier("Expected property name after '.'.")?;
+                expr = Expr::Get(Box::new(expr), name);
             } else {
                 break;
             }
@@ -433,11 +619,25 @@ impl Parser {
 
     fn finish_call(&mut self, callee: &Expr) -> ParseResult<Expr> {
         let mut args = Vec::new();
+        let mut keywords = Vec::new();
 
         if !self.check(&TokenType::RightParen) {
             loop {
-                args.push(self.expression()?);
-                if args.len() >= 255 {
+                let is_keyword = matches!(self.peek().token_type, TokenType::Identifier(_))
+                    && matches!(self.peek_next().token_type, TokenType::Colon);
+                if is_keyword {
+                    let name = self.advance().clone();
+                    self.advance();
+                    keywords.push((name, self.expression()?));
+                } else if !keywords.is_empty() {
+                    return Err(LoxError::parse_error(
+                        &self.peek(),
+                        "Positional arguments cannot follow keyword arguments.",
+                    ));
+                } else {
+                    args.push(self.expression()?);
+                }
+                if args.len() + keywords.len() >= 255 {
                     return Err(LoxError::parse_error(
                         self.previous(),
                         "Function call cannot have more than 255 arguments.".to_string(),
@@ -451,39 +651,53 @@ impl Parser {
 
         let paren = self.consume(&TokenType::RightParen, "Expected ')' after argument list.")?;
 
-        return Ok(Expr::Call(Box::new(callee.clone()), paren.clone(), args));
+        return Ok(Expr::Call(
+            Box::new(callee.clone()),
+            paren.clone(),
+            args,
+            keywords,
+        ));
     }
 
     fn primary(&mut self) -> ParseResult<Expr> {
         match self.peek().token_type {
+            TokenType::Fun => {
+                let keyword = self.advance().clone();
+                self.consume(&TokenType::LeftParen, "Expected '(' after 'fun'.")?;
+                let (params, body) = self.function_rest("function")?;
+                return Ok(Expr::Function(keyword, params, body));
+            }
             TokenType::False => {
-                self.advance();
-                return Ok(Expr::Literal(Literal::Boolean(false)));
+                let line = self.advance().line;
+                return Ok(Expr::Literal(Literal::Boolean(false), line));
             }
             TokenType::True => {
-                self.advance();
-                return Ok(Expr::Literal(Literal::Boolean(true)));
+                let line = self.advance().line;
+                return Ok(Expr::Literal(Literal::Boolean(true), line));
             }
             TokenType::Nil => {
-                self.advance();
-                return Ok(Expr::Literal(Literal::Nil));
+                let line = self.advance().line;
+                return Ok(Expr::Literal(Literal::Nil, line));
             }
             TokenType::Number(value) => {
-                self.advance();
-                return Ok(Expr::Literal(Literal::Number(value)));
+                let line = self.advance().line;
+                return Ok(Expr::Literal(Literal::Number(value), line));
             }
             TokenType::String(value) => {
-                self.advance();
-                return Ok(Expr::Literal(Literal::String(value.clone())));
+                let line = self.advance().line;
+                return Ok(Expr::Literal(Literal::string(value.as_str()), line));
             }
+            TokenType::Interpolation(_) => self.interpolation(),
             TokenType::LeftParen => {
-                self.advance();
+                let line = self.advance().line;
                 match self.expression() {
                     Err(reason) => Err(reason),
                     Ok(expr) => {
                         match self.consume(&TokenType::RightParen, "Expected ')' after expression.")
                         {
-                            Ok(_) => Ok(Expr::Grouping(Box::new(expr))),
+                            // `((x))` means no more than `(x)`, so keep one group.
+                            Ok(_) if matches!(expr, Expr::Grouping(..)) => Ok(expr),
+                            Ok(_) => Ok(Expr::Grouping(Box::new(expr), line)),
                             Err(reason) => Err(reason),
                         }
                     }
@@ -492,13 +706,50 @@ impl Parser {
             TokenType::Identifier(_) => {
                 return Ok(Expr::Var(self.advance().to_owned()));
             }
+            TokenType::This => {
+                return Ok(Expr::This(self.advance().to_owned()));
+            }
+            TokenType::Super => {
+                let keyword = self.advance().to_owned();
+                self.consume(&TokenType::Dot, "Expected '.' after 'super'.")?;
+                let method = self.consume_identifier("Expected superclass method name.")?;
+                return Ok(Expr::Super(keyword, method));
+            }
             _ => Err(LoxError::parse_error(
-                self.previous(),
+                &self.peek(),
                 "Expected expression".to_string(),
             )),
         }
     }
 
+    fn interpolation(&mut self) -> ParseResult<Expr> {
+        let start = self.peek().clone();
+        let mut parts = Vec::new();
+        loop {
+            let token = self.advance().clone();
+            match token.token_type {
+                TokenType::Interpolation(segment) => {
+                    if !segment.is_empty() {
+                        parts.push(Expr::Literal(Literal::string(segment), token.line));
+                    }
+                    parts.push(self.expression()?);
+                }
+                TokenType::String(segment) => {
+                    if !segment.is_empty() {
+                        parts.push(Expr::Literal(Literal::string(segment), token.line));
+                    }
+                    return Ok(Expr::Interpolation(parts, start));
+                }
+                _ => {
+                    return Err(LoxError::parse_error(
+                        self.previous(),
+                        "Expected '}' after interpolated expression.",
+                    ))
+                }
+            }
+        }
+    }
+
     fn consume(&mut self, token_type: &TokenType, msg: &str) -> ParseResult<&Token> {
         if self.check(token_type) {
             return Ok(self.advance());
@@ -536,13 +787,325 @@ impl Parser {
     }
 
     fn is_at_end(&mut self) -> bool {
-        match self.peek().token_type {
-            TokenType::EOF => true,
-            _ => false,
-        }
+        matches!(self.peek().token_type, TokenType::EOF)
     }
 
     fn peek(&self) -> Token {
         self.tokens.get(self.current).unwrap().to_owned()
     }
+
+    fn peek_next(&self) -> Token {
+        match self.tokens.get(self.current + 1) {
+            Some(token) => token.to_owned(),
+            None => self.tokens.last().unwrap().to_owned(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{errors::LoxErrorType, scanner::Scanner};
+
+    fn parse(source: &str) -> Result<Vec<Stmt>, Vec<LoxError>> {
+        let tokens = Scanner::new(source.to_string()).scan_tokens().unwrap();
+        Parser::new(tokens).parse()
+    }
+
+    #[test]
+    fn test_parse_default_parameter() {
+        let program = parse("fun f(a, b = 2) {}").unwrap();
+        match &program[0] {
+            Stmt::Function(_, params, _) => {
+                assert!(params[0].default.is_none());
+                assert_eq!("Some(2)", format!("{:?}", params[1].default));
+            }
+            other => panic!("Expected function, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_required_parameter_after_default() {
+        let errors = parse("fun f(a = 1, b) {}").unwrap_err();
+        assert_eq!(1, errors.len());
+        assert_eq!("b", errors[0].token.lexeme);
+    }
+
+    #[test]
+    fn test_parse_rest_parameter() {
+        let program = parse("fun f(a, ...rest) {}").unwrap();
+        match &program[0] {
+            Stmt::Function(_, params, _) => {
+                assert!(!params[0].rest);
+                assert!(params[1].rest);
+                assert_eq!("rest", params[1].name.lexeme);
+            }
+            other => panic!("Expected function, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_rest_parameter_must_be_last() {
+        let errors = parse("fun f(...rest, a) {}").unwrap_err();
+        assert_eq!(1, errors.len());
+        assert_eq!(
+            LoxErrorType::SyntaxError("A rest parameter must be the last parameter.".to_string()),
+            errors[0].kind
+        );
+    }
+
+    #[test]
+    fn test_parse_keyword_arguments() {
+        let program = parse("f(1, y: 2);").unwrap();
+        assert_eq!(
+            "[Expression(((var f) \"1 y: 2\"))]",
+            format!("{:?}", program)
+        );
+    }
+
+    #[test]
+    fn test_positional_after_keyword_argument() {
+        let errors = parse("f(x: 1, 2);").unwrap_err();
+        assert_eq!(
+            LoxErrorType::SyntaxError(
+                "Positional arguments cannot follow keyword arguments.".to_string()
+            ),
+            errors[0].kind
+        );
+    }
+
+    #[test]
+    fn test_nodes_carry_their_line() {
+        let statements = parse("{\n  (\n    \"a ${b}\");\n}").unwrap();
+        assert_eq!(1, statements[0].line());
+        let Stmt::Block(body, _) = &statements[0] else {
+            panic!("expected a block");
+        };
+        assert_eq!(2, body[0].line());
+        let Stmt::Expression(Expr::Grouping(inner, _)) = &body[0] else {
+            panic!("expected a grouping");
+        };
+        assert_eq!(3, inner.line());
+    }
+
+    #[test]
+    fn test_parse_property_access() {
+        let statements = parse("a.b; a.b.c; a.b = 1; a.b.c = a.d;").unwrap();
+        let printed: Vec<_> = statements
+            .iter()
+            .map(|stmt| match stmt {
+                Stmt::Expression(expr) => format!("{:?}", expr),
+                other => panic!("expected an expression, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                "(. (var a) b)",
+                "(. (. (var a) b) c)",
+                "(set (var a) b 1)",
+                "(set (. (var a) b) c (. (var a) d))",
+            ],
+            printed
+        );
+        assert!(parse("a.1;").is_err());
+    }
+
+    #[test]
+    fn test_reports_every_error_in_a_block() {
+        let errors = parse("{\n  var = 1;\n  print 2;\n  print ;\n}\nprint 3;").unwrap_err();
+        let lines: Vec<_> = errors.iter().map(|error| error.line).collect();
+        assert_eq!(vec![2, 4], lines);
+
+        let errors = parse("fun f() {\n  1 +;\n  2 +;\n}").unwrap_err();
+        assert_eq!(2, errors.len());
+    }
+
+    #[test]
+    fn test_error_count_is_capped() {
+        let source = "print ;\n".repeat(50);
+        let errors = parse(&source).unwrap_err();
+        assert_eq!(21, errors.len());
+        assert_eq!(
+            LoxErrorType::SyntaxError("Too many errors, giving up.".to_string()),
+            errors[20].kind
+        );
+        assert_eq!(20, errors[20].line);
+
+        let tokens = Scanner::new("{ ".repeat(10) + &"print ;".repeat(10))
+            .scan_tokens()
+            .unwrap();
+        let mut parser = Parser::new(tokens);
+        parser.max_errors = 3;
+        assert_eq!(4, parser.parse().unwrap_err().len());
+    }
+
+    #[test]
+    fn test_zero_error_cap_reports_the_first_error() {
+        let tokens = Scanner::new("print ;\n".repeat(5)).scan_tokens().unwrap();
+        let mut parser = Parser::new(tokens);
+        parser.max_errors = 0;
+        let errors = parser.parse().unwrap_err();
+        assert_eq!(2, errors.len());
+        assert_eq!(1, errors[0].line);
+        assert_eq!(
+            LoxErrorType::SyntaxError("Too many errors, giving up.".to_string()),
+            errors[1].kind
+        );
+    }
+
+    #[test]
+    fn test_power_is_right_associative() {
+        let statements = parse("2 ** 3 ** 2; 2 * 3 ** 2; -2 ** 2;").unwrap();
+        let printed: Vec<_> = statements
+            .iter()
+            .map(|stmt| match stmt {
+                Stmt::Expression(expr) => format!("{:?}", expr),
+                other => panic!("expected an expression, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            vec!["(** 2 (** 3 2))", "(* 2 (** 3 2))", "(** (- 2) 2)"],
+            printed
+        );
+    }
+
+    #[test]
+    fn test_nested_groupings_collapse() {
+        let statements = parse("((1)); (((1 + 2))) * 3; (-(1));").unwrap();
+        let printed: Vec<_> = statements
+            .iter()
+            .map(|stmt| match stmt {
+                Stmt::Expression(expr) => format!("{:?}", expr),
+                other => panic!("expected an expression, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                "(group 1)",
+                "(* (group (+ 1 2)) 3)",
+                "(group (- (group 1)))"
+            ],
+            printed
+        );
+    }
+
+    #[test]
+    fn test_parse_empty_class() {
+        let program = parse("class Empty {}").unwrap();
+        match &program[0] {
+            Stmt::Class(name, _, methods) => {
+                assert_eq!("Empty", name.lexeme);
+                assert!(methods.is_empty());
+            }
+            other => panic!("Expected class, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_class_with_methods() {
+        let program = parse("class Pair { first() { return 1; } second(x) {} }").unwrap();
+        match &program[0] {
+            Stmt::Class(_, _, methods) => {
+                let names: Vec<_> = methods
+                    .iter()
+                    .map(|method| match method {
+                        Stmt::Function(name, params, _) => (name.lexeme.as_str(), params.len()),
+                        other => panic!("Expected method, got {:?}", other),
+                    })
+                    .collect();
+                assert_eq!(vec![("first", 0), ("second", 1)], names);
+            }
+            other => panic!("Expected class, got {:?}", other),
+        }
+        assert!(parse("class Broken { var x; }").is_err());
+    }
+
+    #[test]
+    fn test_calls_and_gets_chain_left_to_right() {
+        let statements = parse("a.b().c.d(x); f()().g; a.b.c();").unwrap();
+        let printed: Vec<_> = statements
+            .iter()
+            .map(|stmt| match stmt {
+                Stmt::Expression(expr) => format!("{:?}", expr),
+                other => panic!("expected an expression, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                r#"((. (. ((. (var a) b) "") c) d) "(var x)")"#,
+                r#"(. (((var f) "") "") g)"#,
+                r#"((. (. (var a) b) c) "")"#,
+            ],
+            printed
+        );
+    }
+
+    #[test]
+    fn test_parse_superclass_and_super() {
+        let program = parse("class B < A { f() { return super.f(this); } }").unwrap();
+        match &program[0] {
+            Stmt::Class(_, Some(superclass), methods) => {
+                assert_eq!("(var A)", format!("{:?}", superclass));
+                assert_eq!(1, methods.len());
+            }
+            other => panic!("Expected subclass, got {:?}", other),
+        }
+        assert!(parse("class B < {}").is_err());
+        assert!(parse("super;").is_err());
+    }
+
+    #[test]
+    fn test_break_only_inside_loops() {
+        assert!(parse("while (true) break;").is_ok());
+        assert!(parse("for (;;) { if (true) break; }").is_ok());
+
+        let errors = parse("break;").unwrap_err();
+        assert_eq!(
+            "[line 1] Error at 'break': Can't use 'break' outside of a loop.",
+            errors[0].to_string()
+        );
+        assert!(parse("while (true) { fun f() { break; } }").is_err());
+    }
+
+    #[test]
+    fn test_continue_only_inside_loops() {
+        assert!(parse("while (true) continue;").is_ok());
+
+        let errors = parse("continue;").unwrap_err();
+        assert_eq!(
+            "[line 1] Error at 'continue': Can't use 'continue' outside of a loop.",
+            errors[0].to_string()
+        );
+    }
+
+    #[test]
+    fn test_parse_ternary() {
+        let expression = |source: &str| match &parse(source).unwrap()[0] {
+            Stmt::Expression(expr) => format!("{:?}", expr),
+            other => panic!("expected an expression, got {:?}", other),
+        };
+        assert_eq!("(?: (var a) (var b) (var c))", expression("a ? b : c;"));
+        assert_eq!(
+            "(?: (var a) (var b) (?: (var c) (var d) (var e)))",
+            expression("a ? b : c ? d : e;")
+        );
+        assert_eq!(
+            "(?: (or (var a) (var b)) (assign x 1) (var y))",
+            expression("a or b ? x = 1 : y;")
+        );
+        assert_eq!("(assign x (?: (var a) 1 2))", expression("x = a ? 1 : 2;"));
+        assert!(parse("a ? b;").is_err());
+    }
+
+    #[test]
+    fn test_parse_anonymous_function() {
+        let program = parse("var f = fun (a, b) { return a; };").unwrap();
+        match &program[0] {
+            Stmt::Var(_, Some(value)) => assert_eq!("(fun (a b))", format!("{:?}", value)),
+            other => panic!("expected a variable, got {:?}", other),
+        }
+        assert!(parse("fun (x) { print x; }(1);").is_ok());
+        assert!(parse("while (true) { var f = fun () { break; }; }").is_err());
+        assert!(parse("var f = fun x() {};").is_err());
+    }
 }
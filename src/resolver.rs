@@ -1,43 +1,620 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt::Display};
 
-use crate::{errors::LoxError, stmt::Stmt, token::Token};
+use crate::{
+    expr::{Expr, ExprVisitor},
+    literal::Literal,
+    stmt::{Parameter, Stmt, StmtVisitor},
+    token::Token,
+};
+
+/// The state of a local name within its scope. Names declared by a `var`
+/// anywhere in a block start out `Pending`, so a reference in the same
+/// scope that comes before the declaration can be reported. A reference
+/// from an inner scope, such as a closure, looks past it to the outer
+/// binding.
+/// Function declarations are hoisted: they are `Defined` for the whole
+/// block, which lets sibling functions call each other.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Binding {
+    Pending,
+    Declared,
+    Defined,
+}
 
 pub struct Resolver {
-    scopes: Vec<HashMap<String, bool>>,
+    scopes: Vec<HashMap<String, Binding>>,
+    unread: Vec<HashMap<String, Token>>,
+    locals: ResolutionMap,
+    errors: Vec<ResolutionError>,
+    warnings: Vec<ResolutionWarning>,
+    /// How many function bodies enclose the code being resolved.
+    function_depth: usize,
+}
+
+/// Where the resolver found a variable reference bound.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Resolution {
+    /// This many scopes out from the reference.
+    Local(usize),
+    /// In the globals, since no enclosing scope binds it yet.
+    Global,
+}
+
+pub type ResolutionMap = HashMap<Token, Resolution>;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ResolutionError {
+    ReadInOwnInitializer(Token),
+    UsedBeforeDeclaration(Token),
+    ReturnOutsideFunction(Token),
+    InheritFromSelf(Token),
+}
+
+impl ResolutionError {
+    pub fn token(&self) -> &Token {
+        match self {
+            Self::ReadInOwnInitializer(token)
+            | Self::UsedBeforeDeclaration(token)
+            | Self::ReturnOutsideFunction(token)
+            | Self::InheritFromSelf(token) => token,
+        }
+    }
+
+    pub fn message(&self) -> &'static str {
+        match self {
+            Self::ReadInOwnInitializer(_) => "Can't read local variable in its own initializer.",
+            Self::UsedBeforeDeclaration(_) => "Local variable is used before its declaration.",
+            Self::ReturnOutsideFunction(_) => "Can't return from top-level code.",
+            Self::InheritFromSelf(_) => "A class can't inherit from itself.",
+        }
+    }
 }
 
-pub type ResolutionMap = HashMap<Token, usize>;
+impl Display for ResolutionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let token = self.token();
+        write!(
+            f,
+            "[line {}] Error at '{}': {}",
+            token.line,
+            token.lexeme,
+            self.message()
+        )
+    }
+}
 
+/// Non-fatal diagnostics about code that is legal but probably a mistake.
 #[derive(Clone, Debug, PartialEq, Eq)]
-pub enum ResolutionError {}
+pub enum ResolutionWarning {
+    /// An expression statement without calls or assignments, such as
+    /// `1 + 1;`, whose value is computed and then thrown away.
+    PureExpressionStatement(usize),
+    /// A local variable that is declared but never read.
+    UnusedVariable(Token),
+}
+
+impl Display for ResolutionWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::PureExpressionStatement(line) => write!(
+                f,
+                "[line {}] Warning: Expression statement has no effect.",
+                line
+            ),
+            Self::UnusedVariable(token) => write!(
+                f,
+                "[line {}] Warning at '{}': Local variable is never read.",
+                token.line, token.lexeme
+            ),
+        }
+    }
+}
+
+pub type ResolutionResult<T> = Result<T, Vec<ResolutionError>>;
+
+/// Resolves a whole program with a fresh resolver, discarding warnings.
+/// Hand the map to `Interpreter::resolve` before running the program.
+pub fn resolve_program(statements: &[Stmt]) -> ResolutionResult<ResolutionMap> {
+    return Resolver::new().resolve_statements(statements);
+}
+
+/// Whether evaluating the expression could have an observable effect.
+fn has_side_effects(expr: &Expr) -> bool {
+    match expr {
+        Expr::Call(..) | Expr::Assign(..) | Expr::Set(..) => true,
+        Expr::Binary(left, _, right) | Expr::Logical(left, _, right) => {
+            has_side_effects(left) || has_side_effects(right)
+        }
+        Expr::Ternary(condition, then_branch, else_branch) => {
+            has_side_effects(condition)
+                || has_side_effects(then_branch)
+                || has_side_effects(else_branch)
+        }
+        Expr::Grouping(expr, _) | Expr::Unary(_, expr) | Expr::Get(expr, _) => {
+            has_side_effects(expr)
+        }
+        Expr::Interpolation(parts, _) => parts.iter().any(has_side_effects),
+        Expr::Literal(..) | Expr::Var(_) | Expr::This(_) | Expr::Super(..) | Expr::Function(..) => {
+            false
+        }
+    }
+}
 
-pub type ResolutionResult<T> = Result<T, ResolutionError>;
+impl Default for Resolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl Resolver {
     #[must_use]
-    fn new() -> Self {
-        Self { scopes: Vec::new() }
+    pub fn new() -> Self {
+        Self {
+            scopes: Vec::new(),
+            unread: Vec::new(),
+            locals: HashMap::new(),
+            errors: Vec::new(),
+            warnings: Vec::new(),
+            function_depth: 0,
+        }
+    }
+
+    /// Resolves every variable reference in a program to the number of
+    /// scopes between the reference and its binding, or to the globals.
+    pub fn resolve_statements(&mut self, statements: &[Stmt]) -> ResolutionResult<ResolutionMap> {
+        self.resolve(statements);
+        if self.errors.is_empty() {
+            return Ok(std::mem::take(&mut self.locals));
+        }
+        Err(std::mem::take(&mut self.errors))
+    }
+
+    pub fn warnings(&self) -> &[ResolutionWarning] {
+        &self.warnings
     }
 
-    pub fn visit_statement(&mut self, stmt: Stmt) -> ResolutionResult<()> {
-        match stmt {
-            Stmt::Block(statements) => {
-                self.begin_scope();
-                self.resolve(statements);
-                self.end_scope();
-                Ok(())
+    pub fn visit_statement(&mut self, stmt: &Stmt) {
+        stmt.accept(self)
+    }
+
+    fn visit_expression(&mut self, expr: &Expr) {
+        expr.accept(self)
+    }
+
+    fn resolve(&mut self, statements: &[Stmt]) {
+        for stmt in statements {
+            self.visit_statement(stmt);
+        }
+    }
+
+    fn resolve_function(&mut self, params: &[Parameter], body: &[Stmt]) {
+        self.begin_scope(body);
+        for param in params {
+            if let Some(default) = &param.default {
+                self.visit_expression(default);
             }
-            _ => {
-                unimplemented!()
+            self.declare(&param.name);
+            self.define(&param.name);
+        }
+        self.function_depth += 1;
+        self.resolve(body);
+        self.function_depth -= 1;
+        self.end_scope();
+    }
+
+    fn resolve_local(&mut self, name: &Token) {
+        let innermost = self.scopes.len();
+        for (i, scope) in self.scopes.iter().enumerate().rev() {
+            match scope.get(&name.lexeme) {
+                Some(Binding::Pending) if i + 1 == innermost => {
+                    self.errors
+                        .push(ResolutionError::UsedBeforeDeclaration(name.clone()));
+                    return;
+                }
+                Some(Binding::Pending) => (),
+                Some(Binding::Declared) if i + 1 == innermost => {
+                    self.errors
+                        .push(ResolutionError::ReadInOwnInitializer(name.clone()));
+                    return;
+                }
+                Some(_) => {
+                    self.locals
+                        .insert(name.clone(), Resolution::Local(innermost - 1 - i));
+                    return;
+                }
+                None => (),
             }
         }
+        self.locals.insert(name.clone(), Resolution::Global);
     }
 
-    fn begin_scope(&mut self) {
-        self.scopes.push(HashMap::new());
+    fn mark_read(&mut self, name: &Token) {
+        let found = self
+            .scopes
+            .iter()
+            .rposition(|scope| scope.contains_key(&name.lexeme));
+        if let Some(i) = found {
+            self.unread[i].remove(&name.lexeme);
+        }
+    }
+
+    fn declare(&mut self, name: &Token) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.lexeme.clone(), Binding::Declared);
+        }
+    }
+
+    fn define(&mut self, name: &Token) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.lexeme.clone(), Binding::Defined);
+        }
+    }
+
+    /// Opens a scope for a block, marking the variables it declares as
+    /// pending until their declaration is reached and hoisting the
+    /// functions it declares.
+    fn begin_scope(&mut self, statements: &[Stmt]) {
+        let mut scope = HashMap::new();
+        for stmt in statements {
+            match stmt {
+                Stmt::Var(name, _) | Stmt::Class(name, _, _) => {
+                    scope.insert(name.lexeme.clone(), Binding::Pending);
+                }
+                Stmt::Function(name, _, _) => {
+                    scope.insert(name.lexeme.clone(), Binding::Defined);
+                }
+                _ => (),
+            }
+        }
+        self.scopes.push(scope);
+        self.unread.push(HashMap::new());
     }
 
     fn end_scope(&mut self) {
         self.scopes.pop();
+        if let Some(unread) = self.unread.pop() {
+            let mut unread: Vec<_> = unread.into_values().collect();
+            unread.sort_by_key(|token| token.offset);
+            self.warnings
+                .extend(unread.into_iter().map(ResolutionWarning::UnusedVariable));
+        }
+    }
+}
+
+impl StmtVisitor<()> for Resolver {
+    fn visit_print_stmt(&mut self, expr: &Expr) {
+        self.visit_expression(expr);
+    }
+
+    fn visit_expression_stmt(&mut self, expr: &Expr) {
+        if !has_side_effects(expr) {
+            self.warnings
+                .push(ResolutionWarning::PureExpressionStatement(expr.line()));
+        }
+        self.visit_expression(expr);
+    }
+
+    fn visit_var_stmt(&mut self, name: &Token, initializer: Option<&Expr>) {
+        self.declare(name);
+        if let Some(initializer) = initializer {
+            self.visit_expression(initializer);
+        }
+        self.define(name);
+        if let Some(unread) = self.unread.last_mut() {
+            unread.insert(name.lexeme.clone(), name.clone());
+        }
+    }
+
+    /// A block that declares nothing gets no scope, just as the
+    /// interpreter gives it no environment, so depths line up.
+    fn visit_block_stmt(&mut self, statements: &[Stmt], _line: usize) {
+        if !statements.iter().any(Stmt::is_declaration) {
+            return self.resolve(statements);
+        }
+        self.begin_scope(statements);
+        self.resolve(statements);
+        self.end_scope();
+    }
+
+    fn visit_if_stmt(&mut self, condition: &Expr, then_branch: &Stmt, else_branch: Option<&Stmt>) {
+        self.visit_expression(condition);
+        self.visit_statement(then_branch);
+        if let Some(else_branch) = else_branch {
+            self.visit_statement(else_branch);
+        }
+    }
+
+    fn visit_while_stmt(&mut self, condition: &Expr, body: &Stmt) {
+        self.visit_expression(condition);
+        self.visit_statement(body);
+    }
+
+    /// The initializer gets a scope of its own around the whole loop, as
+    /// in the interpreter.
+    fn visit_for_stmt(
+        &mut self,
+        initializer: Option<&Stmt>,
+        condition: &Expr,
+        increment: Option<&Expr>,
+        body: &Stmt,
+    ) {
+        let Some(initializer) = initializer else {
+            self.visit_expression(condition);
+            self.visit_statement(body);
+            if let Some(increment) = increment {
+                self.visit_expression(increment);
+            }
+            return;
+        };
+        self.begin_scope(std::slice::from_ref(initializer));
+        self.visit_statement(initializer);
+        self.visit_for_stmt(None, condition, increment, body);
+        self.end_scope();
+    }
+
+    fn visit_function_stmt(&mut self, name: &Token, params: &[Parameter], body: &[Stmt]) {
+        self.declare(name);
+        self.define(name);
+        self.resolve_function(params, body);
+    }
+
+    fn visit_return_stmt(&mut self, keyword: &Token, value: Option<&Expr>) {
+        if self.function_depth == 0 {
+            self.errors
+                .push(ResolutionError::ReturnOutsideFunction(keyword.clone()));
+        }
+        if let Some(value) = value {
+            self.visit_expression(value);
+        }
+    }
+
+    fn visit_break_stmt(&mut self, _keyword: &Token) {}
+
+    fn visit_continue_stmt(&mut self, _keyword: &Token) {}
+
+    /// Methods are resolved inside a scope binding `this`, itself inside
+    /// one binding `super` when there is a superclass, mirroring the
+    /// environments the interpreter builds.
+    fn visit_class_stmt(&mut self, name: &Token, superclass: Option<&Expr>, methods: &[Stmt]) {
+        self.declare(name);
+        self.define(name);
+        if let Some(superclass) = superclass {
+            if let Expr::Var(superclass_name) = superclass {
+                if superclass_name.same_kind(name) {
+                    self.errors
+                        .push(ResolutionError::InheritFromSelf(superclass_name.clone()));
+                }
+            }
+            self.visit_expression(superclass);
+            self.begin_scope(&[]);
+            self.scopes
+                .last_mut()
+                .unwrap()
+                .insert("super".to_string(), Binding::Defined);
+        }
+        self.begin_scope(&[]);
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .insert("this".to_string(), Binding::Defined);
+        for method in methods {
+            if let Stmt::Function(_, params, body) = method {
+                self.resolve_function(params, body);
+            }
+        }
+        self.end_scope();
+        if superclass.is_some() {
+            self.end_scope();
+        }
+    }
+}
+
+impl ExprVisitor<()> for Resolver {
+    fn visit_assign_expr(&mut self, name: &Token, value: &Expr) {
+        self.visit_expression(value);
+        self.resolve_local(name);
+    }
+
+    fn visit_binary_expr(&mut self, left: &Expr, _operator: &Token, right: &Expr) {
+        self.visit_expression(left);
+        self.visit_expression(right);
+    }
+
+    fn visit_call_expr(
+        &mut self,
+        callee: &Expr,
+        _paren: &Token,
+        arguments: &[Expr],
+        keywords: &[(Token, Expr)],
+    ) {
+        self.visit_expression(callee);
+        for argument in arguments {
+            self.visit_expression(argument);
+        }
+        for (_, argument) in keywords {
+            self.visit_expression(argument);
+        }
+    }
+
+    fn visit_get_expr(&mut self, object: &Expr, _name: &Token) {
+        self.visit_expression(object);
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &Expr, _line: usize) {
+        self.visit_expression(expr);
+    }
+
+    fn visit_interpolation_expr(&mut self, parts: &[Expr], _start: &Token) {
+        for part in parts {
+            self.visit_expression(part);
+        }
+    }
+
+    fn visit_literal_expr(&mut self, _value: &Literal, _line: usize) {}
+
+    fn visit_logical_expr(&mut self, left: &Expr, _operator: &Token, right: &Expr) {
+        self.visit_expression(left);
+        self.visit_expression(right);
+    }
+
+    fn visit_function_expr(&mut self, _keyword: &Token, params: &[Parameter], body: &[Stmt]) {
+        self.resolve_function(params, body);
+    }
+
+    fn visit_ternary_expr(&mut self, condition: &Expr, then_branch: &Expr, else_branch: &Expr) {
+        self.visit_expression(condition);
+        self.visit_expression(then_branch);
+        self.visit_expression(else_branch);
+    }
+
+    fn visit_set_expr(&mut self, object: &Expr, _name: &Token, value: &Expr) {
+        self.visit_expression(value);
+        self.visit_expression(object);
+    }
+
+    fn visit_super_expr(&mut self, keyword: &Token, _method: &Token) {
+        self.resolve_local(keyword);
+    }
+
+    fn visit_this_expr(&mut self, keyword: &Token) {
+        self.resolve_local(keyword);
+    }
+
+    fn visit_unary_expr(&mut self, _operator: &Token, right: &Expr) {
+        self.visit_expression(right);
+    }
+
+    fn visit_var_expr(&mut self, name: &Token) {
+        self.resolve_local(name);
+        self.mark_read(name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, scanner::Scanner};
+
+    fn resolve(source: &str) -> ResolutionResult<ResolutionMap> {
+        let tokens = Scanner::new(source.to_string()).scan_tokens().unwrap();
+        let statements = Parser::new(tokens).parse().unwrap();
+        Resolver::new().resolve_statements(&statements)
+    }
+
+    #[test]
+    fn test_use_before_declaration() {
+        let errors = resolve("{\n  print x;\n  var x = 1;\n}").unwrap_err();
+        assert_eq!(1, errors.len());
+        assert_eq!(
+            "[line 2] Error at 'x': Local variable is used before its declaration.",
+            errors[0].to_string()
+        );
+
+        let errors = resolve("var x = 1; { x = 2; var x = 3; }").unwrap_err();
+        assert!(matches!(
+            errors[0],
+            ResolutionError::UsedBeforeDeclaration(_)
+        ));
+    }
+
+    #[test]
+    fn test_read_in_own_initializer() {
+        let errors = resolve("var a = 1; { var a = a; }").unwrap_err();
+        assert!(matches!(
+            errors[0],
+            ResolutionError::ReadInOwnInitializer(_)
+        ));
+    }
+
+    #[test]
+    fn test_globals_and_forward_functions_are_exempt() {
+        assert!(resolve("print x; var x = 1;").is_ok());
+        assert!(resolve("fun a() { return b(); } fun b() { return 1; } print a();").is_ok());
+        assert!(resolve("{ var x = 1; { print x; } }").is_ok());
+    }
+
+    #[test]
+    fn test_closures_see_past_later_declarations() {
+        let source = "var a = 1; { fun show() { return a; } show(); var a = 2; }";
+        let locals = resolve(source).unwrap();
+        let read = locals
+            .iter()
+            .find(|(token, _)| token.lexeme == "a")
+            .unwrap();
+        assert_eq!(Resolution::Global, *read.1);
+    }
+
+    #[test]
+    fn test_local_mutual_recursion() {
+        let source = "{
+            fun isEven(n) { if (n == 0) return true; return isOdd(n - 1); }
+            fun isOdd(n) { if (n == 0) return false; return isEven(n - 1); }
+            print isEven(10);
+        }";
+        let locals = resolve(source).unwrap();
+        let is_odd = locals
+            .iter()
+            .find(|(token, _)| token.lexeme == "isOdd")
+            .unwrap();
+        assert_eq!(Resolution::Local(1), *is_odd.1);
+    }
+
+    fn warnings(source: &str) -> Vec<ResolutionWarning> {
+        let tokens = Scanner::new(source.to_string()).scan_tokens().unwrap();
+        let statements = Parser::new(tokens).parse().unwrap();
+        let mut resolver = Resolver::new();
+        resolver.resolve_statements(&statements).unwrap();
+        resolver.warnings().to_vec()
+    }
+
+    #[test]
+    fn test_pure_expression_statement_warns() {
+        let found = warnings("var x = 1;\n1 + 1;\nx;");
+        assert_eq!(2, found.len());
+        assert_eq!(
+            "[line 2] Warning: Expression statement has no effect.",
+            found[0].to_string()
+        );
+        assert_eq!(
+            "[line 3] Warning: Expression statement has no effect.",
+            found[1].to_string()
+        );
+    }
+
+    #[test]
+    fn test_calls_and_assignments_do_not_warn() {
+        assert!(warnings("fun f() {} var x; f(); x = 1; (x = 2) + 1; !f();").is_empty());
+    }
+
+    #[test]
+    fn test_unused_local_variable_warns() {
+        let found = warnings("var global;\n{\n  var used = 1;\n  var unused = used;\n}");
+        assert_eq!(
+            vec!["[line 4] Warning at 'unused': Local variable is never read.".to_string()],
+            found.iter().map(|w| w.to_string()).collect::<Vec<_>>()
+        );
+        assert!(warnings("fun f(a) { var b = a; return b; }").is_empty());
+    }
+
+    #[test]
+    fn test_return_outside_function() {
+        let errors = resolve("print 1;\nreturn 2;").unwrap_err();
+        assert_eq!(1, errors.len());
+        assert_eq!(
+            "[line 2] Error at 'return': Can't return from top-level code.",
+            errors[0].to_string()
+        );
+        assert!(resolve("fun f() { { return 1; } }").is_ok());
+    }
+
+    #[test]
+    fn test_class_cannot_inherit_from_itself() {
+        let errors = resolve("class A {}\nclass B < B {}").unwrap_err();
+        assert_eq!(1, errors.len());
+        assert_eq!(
+            "[line 2] Error at 'B': A class can't inherit from itself.",
+            errors[0].to_string()
+        );
+        assert!(resolve("class A {} class B < A { f() { return super.f; } }").is_ok());
     }
 }
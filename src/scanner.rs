@@ -1,3 +1,6 @@
+use std::fmt::Display;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
 use crate::token::{Token, TokenType};
 
 #[derive(Clone, Debug)]
@@ -7,10 +10,27 @@ pub struct ScanError {
     pub lexeme: Option<String>,
 }
 
+impl Display for ScanError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.lexeme {
+            Some(lexeme) => write!(
+                f,
+                "[line {}] Error at '{}': {}",
+                self.line, lexeme, self.message
+            ),
+            None => write!(f, "[line {}] Error: {}", self.line, self.message),
+        }
+    }
+}
+
 pub type ScanResult = Result<Vec<Token>, Vec<ScanError>>;
 
+/// Hands every scanner its own source id, starting from 1.
+static NEXT_SOURCE_ID: AtomicUsize = AtomicUsize::new(1);
+
 pub struct Scanner {
     source: Vec<char>,
+    source_id: usize,
     start: usize,
     current: usize,
     line: usize,
@@ -20,11 +40,11 @@ pub struct Scanner {
 }
 
 fn is_digit(c: &char) -> bool {
-    ('0'..='9').contains(c)
+    c.is_ascii_digit()
 }
 
 fn is_alpha(c: &char) -> bool {
-    ('a'..='z').contains(c) || ('A'..='Z').contains(c) || c == &'_'
+    c.is_ascii_lowercase() || c.is_ascii_uppercase() || c == &'_'
 }
 
 fn is_alphanumeric(c: &char) -> bool {
@@ -33,8 +53,15 @@ fn is_alphanumeric(c: &char) -> bool {
 
 impl Scanner {
     pub fn new(source: String) -> Self {
+        // Editors sometimes save UTF-8 with a byte-order mark; it is not
+        // part of the program.
+        let source = match source.strip_prefix('\u{FEFF}') {
+            Some(rest) => rest.to_string(),
+            None => source,
+        };
         Self {
             source: source.chars().collect(),
+            source_id: NEXT_SOURCE_ID.fetch_add(1, Ordering::Relaxed),
             current: 0,
             start: 0,
             line: 1,
@@ -84,7 +111,8 @@ impl Scanner {
 
     fn add_token(&mut self, token_type: TokenType) {
         let lexeme = self.get_current_lexeme();
-        let token = Token::new(token_type, lexeme, self.line, self.current);
+        let mut token = Token::new(token_type, lexeme, self.line, self.current);
+        token.source_id = self.source_id;
         self.tokens.push(token);
     }
 
@@ -95,12 +123,31 @@ impl Scanner {
             ')' => self.add_token(TokenType::RightParen),
             '{' => self.add_token(TokenType::LeftBrace),
             '}' => self.add_token(TokenType::RightBrace),
+            ':' => self.add_token(TokenType::Colon),
+            '?' => self.add_token(TokenType::Question),
             ',' => self.add_token(TokenType::Comma),
-            '.' => self.add_token(TokenType::Dot),
+            '.' => {
+                if self.peek() == Some('.') && self.peek_next() == Some('.') {
+                    self.current += 2;
+                    self.add_token(TokenType::Ellipsis)
+                } else if self.peek().is_some_and(|c| is_digit(&c)) {
+                    // `.5` is shorthand for `0.5`.
+                    self.scan_number()
+                } else {
+                    self.add_token(TokenType::Dot)
+                }
+            }
             '-' => self.add_token(TokenType::Minus),
             '+' => self.add_token(TokenType::Plus),
             ';' => self.add_token(TokenType::Semicolon),
-            '*' => self.add_token(TokenType::Star),
+            '*' => {
+                if self.match_lookahead('*') {
+                    self.add_token(TokenType::StarStar)
+                } else {
+                    self.add_token(TokenType::Star)
+                }
+            }
+            '%' => self.add_token(TokenType::Percent),
             '!' => {
                 if self.match_lookahead('=') {
                     self.add_token(TokenType::BangEqual)
@@ -118,6 +165,8 @@ impl Scanner {
             '<' => {
                 if self.match_lookahead('=') {
                     self.add_token(TokenType::LessEqual)
+                } else if self.match_lookahead('<') {
+                    self.add_token(TokenType::LessLess)
                 } else {
                     self.add_token(TokenType::Less)
                 }
@@ -125,6 +174,8 @@ impl Scanner {
             '>' => {
                 if self.match_lookahead('=') {
                     self.add_token(TokenType::GreaterEqual)
+                } else if self.match_lookahead('>') {
+                    self.add_token(TokenType::GreaterGreater)
                 } else {
                     self.add_token(TokenType::Greater)
                 }
@@ -136,6 +187,8 @@ impl Scanner {
                     while self.peek() != Some('\n') && !self.is_at_end() {
                         self.advance();
                     }
+                } else if self.match_lookahead('*') {
+                    self.skip_block_comment();
                 } else {
                     self.add_token(TokenType::Slash);
                 }
@@ -144,7 +197,14 @@ impl Scanner {
             // Ignore whitespace
             ' ' | '\r' | '\t' => (),
 
-            '"' => self.scan_string(),
+            '"' => {
+                if self.peek() == Some('"') && self.peek_next() == Some('"') {
+                    self.current += 2;
+                    self.scan_triple_quoted_string();
+                } else {
+                    self.scan_string()
+                }
+            }
 
             '0'..='9' => self.scan_number(),
 
@@ -160,6 +220,34 @@ impl Scanner {
         }
     }
 
+    /// Skips a `/* ... */` comment, with the opening delimiter already
+    /// consumed. Block comments do not nest.
+    fn skip_block_comment(&mut self) {
+        let start_line = self.line;
+        loop {
+            match self.peek() {
+                None => {
+                    self.errors.push(ScanError {
+                        line: start_line,
+                        message: "Unterminated block comment".to_string(),
+                        lexeme: None,
+                    });
+                    return;
+                }
+                Some('*') if self.peek_next() == Some('/') => {
+                    self.current += 2;
+                    return;
+                }
+                Some(c) => {
+                    if c == '\n' {
+                        self.line += 1;
+                    }
+                    self.advance();
+                }
+            }
+        }
+    }
+
     fn add_error(&mut self, message: String, lexeme: Option<String>) {
         self.errors.push(ScanError {
             line: self.line,
@@ -168,8 +256,152 @@ impl Scanner {
         })
     }
 
+    /// Scans a string literal. Each `${expr}` inside it ends the current
+    /// segment with an `Interpolation` token, followed by the tokens of
+    /// `expr`; the text after the last interpolation becomes a plain
+    /// `String` token. Backslash escapes are decoded: `\n`, `\t`, `\r`,
+    /// `\0`, `\\`, `\"`, `\$` for a literal dollar sign, and `\u{...}` for
+    /// any Unicode scalar value given in hex.
     fn scan_string(&mut self) {
-        while self.peek() != Some('"') && !self.is_at_end() {
+        let start = self.start;
+        let mut value = String::new();
+        loop {
+            match self.peek() {
+                None => {
+                    self.add_error("Unterminated string literal".to_string(), None);
+                    return;
+                }
+                Some('"') => break,
+                Some('\\') => {
+                    self.advance();
+                    let Some(escaped) = self.peek() else {
+                        self.add_error("Unterminated string literal".to_string(), None);
+                        return;
+                    };
+                    self.advance();
+                    match escaped {
+                        'n' => value.push('\n'),
+                        't' => value.push('\t'),
+                        'r' => value.push('\r'),
+                        '0' => value.push('\0'),
+                        '\\' | '"' | '$' => value.push(escaped),
+                        'u' => value.extend(self.scan_unicode_escape()),
+                        other => self.add_error(
+                            "Unknown escape sequence.".to_string(),
+                            Some(format!("\\{}", other)),
+                        ),
+                    }
+                }
+                Some('$') if self.peek_next() == Some('{') => {
+                    self.current += 2;
+                    let segment = std::mem::take(&mut value);
+                    let mut token = Token::new(
+                        TokenType::Interpolation(segment.clone()),
+                        segment,
+                        self.line,
+                        self.current,
+                    );
+                    token.source_id = self.source_id;
+                    self.tokens.push(token);
+                    if !self.scan_interpolated_expression() {
+                        return;
+                    }
+                }
+                Some(c) => {
+                    if c == '\n' {
+                        self.line += 1;
+                    }
+                    value.push(c);
+                    self.advance();
+                }
+            }
+        }
+
+        self.advance();
+
+        self.start = start;
+        self.add_token(TokenType::String(value));
+    }
+
+    /// Decodes the `{1F600}` part of a `\u{1F600}` escape, with the `\u`
+    /// already consumed. Reports an error and returns `None` if it is
+    /// malformed or names no character.
+    fn scan_unicode_escape(&mut self) -> Option<char> {
+        if self.peek() != Some('{') {
+            self.add_error(
+                "Expected '{' after '\\u'.".to_string(),
+                Some("\\u".to_string()),
+            );
+            return None;
+        }
+        self.advance();
+        let mut digits = String::new();
+        while let Some(c) = self.peek() {
+            if c == '}' || c == '"' || c == '\n' || digits.len() > 8 {
+                break;
+            }
+            digits.push(c);
+            self.advance();
+        }
+        let lexeme = format!("\\u{{{}", digits);
+        if self.peek() != Some('}') {
+            self.add_error("Unterminated unicode escape.".to_string(), Some(lexeme));
+            return None;
+        }
+        self.advance();
+        let decoded = u32::from_str_radix(&digits, 16)
+            .ok()
+            .filter(|_| !digits.starts_with('+'))
+            .and_then(char::from_u32);
+        if decoded.is_none() {
+            self.add_error(
+                "Invalid unicode code point.".to_string(),
+                Some(format!("{}}}", lexeme)),
+            );
+        }
+        return decoded;
+    }
+
+    /// Scans the tokens of an embedded `${...}` expression up to and
+    /// including its closing brace. Returns false if the input ends first.
+    fn scan_interpolated_expression(&mut self) -> bool {
+        let mut depth = 0;
+        loop {
+            match self.peek() {
+                None => {
+                    self.add_error("Unterminated string interpolation".to_string(), None);
+                    return false;
+                }
+                Some('}') if depth == 0 => {
+                    self.advance();
+                    return true;
+                }
+                Some(c) => {
+                    if c == '{' {
+                        depth += 1;
+                    } else if c == '}' {
+                        depth -= 1;
+                    }
+                    self.start = self.current;
+                    self.scan_token();
+                }
+            }
+        }
+    }
+
+    /// Scans a `"""`-delimited string, which may span lines. The contents
+    /// are kept verbatim, indentation included; only a line break directly
+    /// after the opening quotes is dropped, so the text can start on its own
+    /// line.
+    fn scan_triple_quoted_string(&mut self) {
+        let start_line = self.line;
+        while !self.is_at_end() {
+            if self.peek() == Some('"')
+                && self.peek_next() == Some('"')
+                && self.source.get(self.current + 2) == Some(&'"')
+            {
+                break;
+            }
             if self.peek() == Some('\n') {
                 self.line += 1;
             }
@@ -177,14 +409,23 @@ impl Scanner {
         }
 
         if self.is_at_end() {
-            self.add_error("Unterminated string literal".to_string(), None);
+            self.errors.push(ScanError {
+                line: start_line,
+                message: "Unterminated triple-quoted string literal".to_string(),
+                lexeme: None,
+            });
             return;
         }
 
-        self.advance();
+        self.current += 3;
 
-        let range = (self.start + 1)..(self.current - 1);
-        let value: String = self.source[range].iter().collect();
+        let mut contents = (self.start + 3)..(self.current - 3);
+        if self.source.get(contents.start) == Some(&'\n') {
+            contents.start += 1;
+        } else if self.source[contents.clone()].starts_with(&['\r', '\n']) {
+            contents.start += 2;
+        }
+        let value: String = self.source[contents].iter().collect();
         self.add_token(TokenType::String(value));
     }
 
@@ -192,7 +433,15 @@ impl Scanner {
         self.source[self.start..self.current].iter().collect()
     }
 
+    /// Scans a decimal number, with an optional exponent as in `6.02e23`.
+    /// Only `.` separates the fraction, whatever the locale, and nothing
+    /// groups digits: `1,000` is `1`, `,`, `000`.
     fn scan_number(&mut self) {
+        if self.source[self.start] == '0' && matches!(self.peek(), Some('x' | 'X')) {
+            self.advance();
+            return self.scan_hex_number();
+        }
+
         while let Some(digit) = self.peek() {
             if is_digit(&digit) {
                 self.advance();
@@ -201,7 +450,10 @@ impl Scanner {
             }
         }
 
-        if self.peek() == Some('.') {
+        // A number that started at its decimal point already has its
+        // fraction.
+        let leading_point = self.source[self.start] == '.';
+        if !leading_point && self.peek() == Some('.') {
             if let Some(digit) = self.peek_next() {
                 if is_digit(&digit) {
                     self.advance();
@@ -216,11 +468,97 @@ impl Scanner {
             }
         }
 
+        if matches!(self.peek(), Some('e' | 'E')) {
+            self.advance();
+            if matches!(self.peek(), Some('+' | '-')) {
+                self.advance();
+            }
+            let mut exponent_digits = 0;
+            while self.peek().is_some_and(|c| is_digit(&c)) {
+                exponent_digits += 1;
+                self.advance();
+            }
+            if exponent_digits == 0 {
+                let lexeme = self.get_current_lexeme();
+                self.add_error("Expected digits in exponent.".to_string(), Some(lexeme));
+                return;
+            }
+        }
+
         let as_string = self.get_current_lexeme();
         let value: f64 = as_string.parse().unwrap();
         self.add_token(TokenType::Number(value));
     }
 
+    /// Scans a hexadecimal integer such as `0xFF`, or a C-style hexadecimal
+    /// float such as `0x1.8p3` (1.5 * 2^3), with the `0x` already consumed.
+    /// As in C, a hex float needs its binary exponent.
+    fn scan_hex_number(&mut self) {
+        let mut value = 0.0;
+        let mut digits = 0;
+        while let Some(digit) = self.peek().and_then(|c| c.to_digit(16)) {
+            value = value * 16.0 + digit as f64;
+            digits += 1;
+            self.advance();
+        }
+        let is_float = matches!(self.peek(), Some('.' | 'p' | 'P'));
+        if self.peek() == Some('.') {
+            self.advance();
+            let mut scale = 1.0 / 16.0;
+            while let Some(digit) = self.peek().and_then(|c| c.to_digit(16)) {
+                value += digit as f64 * scale;
+                scale /= 16.0;
+                digits += 1;
+                self.advance();
+            }
+        }
+
+        let mut exponent = None;
+        if matches!(self.peek(), Some('p' | 'P')) {
+            self.advance();
+            let negative = self.peek() == Some('-');
+            if matches!(self.peek(), Some('+' | '-')) {
+                self.advance();
+            }
+            let mut magnitude: i32 = 0;
+            let mut exponent_digits = 0;
+            while let Some(digit) = self.peek().and_then(|c| c.to_digit(10)) {
+                magnitude = magnitude.saturating_mul(10).saturating_add(digit as i32);
+                exponent_digits += 1;
+                self.advance();
+            }
+            if exponent_digits > 0 {
+                exponent = Some(if negative { -magnitude } else { magnitude });
+            }
+        }
+
+        // Swallow whatever else is glued on so a bad literal is one error.
+        let mut trailing = false;
+        while self.peek().is_some_and(|c| is_alphanumeric(&c) || c == '.') {
+            trailing = true;
+            self.advance();
+        }
+
+        let lexeme = self.get_current_lexeme();
+        if !is_float {
+            if digits > 0 && !trailing {
+                self.add_token(TokenType::Number(value));
+            } else {
+                self.add_error("Invalid hexadecimal literal.".to_string(), Some(lexeme));
+            }
+            return;
+        }
+        match exponent {
+            Some(exponent) if digits > 0 && !trailing => {
+                self.add_token(TokenType::Number(value * 2f64.powi(exponent)));
+            }
+            _ => self.add_error(
+                "Invalid hexadecimal float literal.".to_string(),
+                Some(lexeme),
+            ),
+        }
+    }
+
     fn scan_identifier(&mut self) {
         while let Some(c) = self.peek() {
             if is_alphanumeric(&c) {
@@ -247,3 +585,305 @@ impl Scanner {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token_types(source: &str) -> Vec<TokenType> {
+        let tokens = Scanner::new(source.to_string()).scan_tokens().unwrap();
+        tokens.into_iter().map(|t| t.token_type).collect()
+    }
+
+    #[test]
+    fn test_triple_quoted_string() {
+        let source = "var s = \"\"\"\n  first \"line\"\n  second\"\"\";\nprint s;";
+        let tokens = Scanner::new(source.to_string()).scan_tokens().unwrap();
+        assert_eq!(
+            TokenType::String("  first \"line\"\n  second".to_string()),
+            tokens[3].token_type
+        );
+        assert_eq!(TokenType::Print, tokens[5].token_type);
+        assert_eq!(4, tokens[5].line);
+    }
+
+    #[test]
+    fn test_empty_string_is_not_triple_quoted() {
+        assert_eq!(
+            vec![
+                TokenType::String("".to_string()),
+                TokenType::Semicolon,
+                TokenType::EOF
+            ],
+            token_types("\"\";")
+        );
+    }
+
+    #[test]
+    fn test_unterminated_triple_quoted_string() {
+        let errors = Scanner::new("\n\"\"\"abc\n\"\"".to_string())
+            .scan_tokens()
+            .unwrap_err();
+        assert_eq!(1, errors.len());
+        assert_eq!(2, errors[0].line);
+        assert_eq!(
+            "Unterminated triple-quoted string literal",
+            errors[0].message
+        );
+    }
+
+    #[test]
+    fn test_string_interpolation_tokens() {
+        assert_eq!(
+            vec![
+                TokenType::Interpolation("Hello, ".to_string()),
+                TokenType::Identifier("name".to_string()),
+                TokenType::String("!".to_string()),
+                TokenType::EOF
+            ],
+            token_types("\"Hello, ${name}!\"")
+        );
+    }
+
+    #[test]
+    fn test_escaped_interpolation() {
+        assert_eq!(
+            vec![
+                TokenType::String("cost: ${price}".to_string()),
+                TokenType::EOF
+            ],
+            token_types("\"cost: \\${price}\"")
+        );
+    }
+
+    #[test]
+    fn test_unterminated_interpolation() {
+        let errors = Scanner::new("\"a ${b".to_string())
+            .scan_tokens()
+            .unwrap_err();
+        assert_eq!("Unterminated string interpolation", errors[0].message);
+    }
+
+    #[test]
+    fn test_hex_float_literals() {
+        for (source, expected) in [
+            ("0x1.8p3", 12.0),
+            ("0x1p-2", 0.25),
+            ("0XA.8P+1", 21.0),
+            ("0x.8p0", 0.5),
+        ] {
+            assert_eq!(
+                vec![TokenType::Number(expected), TokenType::EOF],
+                token_types(source),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_hex_float_literals() {
+        for source in ["0x1.8", "0x1p", "0x.p1", "0x1.8p3z"] {
+            let errors = Scanner::new(source.to_string()).scan_tokens().unwrap_err();
+            assert_eq!(1, errors.len(), "{}", source);
+            assert_eq!(
+                format!(
+                    "[line 1] Error at '{}': Invalid hexadecimal float literal.",
+                    source
+                ),
+                errors[0].to_string()
+            );
+        }
+    }
+
+    #[test]
+    fn test_block_comment() {
+        let source = "1 /* one\ntwo * / three\n*/ 2";
+        let tokens = Scanner::new(source.to_string()).scan_tokens().unwrap();
+        let types: Vec<_> = tokens.iter().map(|t| t.token_type.clone()).collect();
+        assert_eq!(
+            vec![
+                TokenType::Number(1.0),
+                TokenType::Number(2.0),
+                TokenType::EOF
+            ],
+            types
+        );
+        assert_eq!(3, tokens[1].line);
+    }
+
+    #[test]
+    fn test_unterminated_block_comment() {
+        let errors = Scanner::new("1;\n/* never\nclosed *".to_string())
+            .scan_tokens()
+            .unwrap_err();
+        assert_eq!(1, errors.len());
+        assert_eq!(
+            "[line 2] Error: Unterminated block comment",
+            errors[0].to_string()
+        );
+    }
+
+    #[test]
+    fn test_comma_never_groups_digits() {
+        assert_eq!(
+            vec![
+                TokenType::Number(1.0),
+                TokenType::Comma,
+                TokenType::Number(0.0),
+                TokenType::EOF
+            ],
+            token_types("1,000")
+        );
+    }
+
+    #[test]
+    fn test_string_escapes() {
+        assert_eq!(
+            vec![
+                TokenType::String("tab\there \"q\" \\ \n\r\0".to_string()),
+                TokenType::EOF
+            ],
+            token_types(r#""tab\there \"q\" \\ \n\r\0""#)
+        );
+    }
+
+    #[test]
+    fn test_unknown_escape() {
+        let errors = Scanner::new("\n\"\\z\"".to_string())
+            .scan_tokens()
+            .unwrap_err();
+        assert_eq!(1, errors.len());
+        assert_eq!(
+            "[line 2] Error at '\\z': Unknown escape sequence.",
+            errors[0].to_string()
+        );
+    }
+
+    #[test]
+    fn test_backslash_at_end_of_input() {
+        let errors = Scanner::new("\"abc\\".to_string())
+            .scan_tokens()
+            .unwrap_err();
+        assert_eq!("Unterminated string literal", errors[0].message);
+    }
+
+    #[test]
+    fn test_unicode_escapes() {
+        assert_eq!(
+            vec![
+                TokenType::String("caf\u{e9} \u{1F600}".to_string()),
+                TokenType::EOF
+            ],
+            token_types(r#""caf\u{e9} \u{1F600}""#)
+        );
+    }
+
+    #[test]
+    fn test_malformed_unicode_escapes() {
+        let cases = [
+            (r#""\u00e9""#, "Error at '\\u': Expected '{' after '\\u'."),
+            (
+                r#""\u{e9""#,
+                "Error at '\\u{e9': Unterminated unicode escape.",
+            ),
+            (r#""\u{}""#, "Error at '\\u{}': Invalid unicode code point."),
+            (
+                r#""\u{xyz}""#,
+                "Error at '\\u{xyz}': Invalid unicode code point.",
+            ),
+            (
+                r#""\u{D800}""#,
+                "Error at '\\u{D800}': Invalid unicode code point.",
+            ),
+            (
+                r#""\u{110000}""#,
+                "Error at '\\u{110000}': Invalid unicode code point.",
+            ),
+        ];
+        for (source, expected) in cases {
+            let errors = Scanner::new(source.to_string()).scan_tokens().unwrap_err();
+            assert_eq!(1, errors.len(), "{}", source);
+            assert_eq!(format!("[line 1] {}", expected), errors[0].to_string());
+        }
+    }
+
+    #[test]
+    fn test_leading_byte_order_mark_is_ignored() {
+        let tokens = Scanner::new("\u{FEFF}var x;\nx;".to_string())
+            .scan_tokens()
+            .unwrap();
+        assert_eq!(TokenType::Var, tokens[0].token_type);
+        assert_eq!("var", tokens[0].lexeme);
+        assert_eq!((1, 3), (tokens[0].line, tokens[0].offset));
+        assert_eq!(2, tokens[3].line);
+    }
+
+    #[test]
+    fn test_scientific_notation() {
+        for (source, expected) in [("1e3", 1000.0), ("1.5E-2", 0.015), ("6.02e+23", 6.02e23)] {
+            assert_eq!(
+                vec![TokenType::Number(expected), TokenType::EOF],
+                token_types(source),
+                "{}",
+                source
+            );
+        }
+        let errors = Scanner::new("2e;".to_string()).scan_tokens().unwrap_err();
+        assert_eq!(
+            "[line 1] Error at '2e': Expected digits in exponent.",
+            errors[0].to_string()
+        );
+    }
+
+    #[test]
+    fn test_hex_integer_literals() {
+        for (source, expected) in [("0x10", 16.0), ("0xdead", 57005.0), ("0XFF", 255.0)] {
+            assert_eq!(
+                vec![TokenType::Number(expected), TokenType::EOF],
+                token_types(source),
+                "{}",
+                source
+            );
+        }
+        for source in ["0x", "0x1g", "0xz"] {
+            let errors = Scanner::new(source.to_string()).scan_tokens().unwrap_err();
+            assert_eq!(
+                format!(
+                    "[line 1] Error at '{}': Invalid hexadecimal literal.",
+                    source
+                ),
+                errors[0].to_string()
+            );
+        }
+    }
+
+    #[test]
+    fn test_leading_decimal_point() {
+        assert_eq!(
+            vec![TokenType::Number(0.5), TokenType::EOF],
+            token_types(".5")
+        );
+        assert_eq!(
+            vec![TokenType::Number(250.0), TokenType::EOF],
+            token_types(".25e3")
+        );
+        assert_eq!(
+            vec![
+                TokenType::Number(0.5),
+                TokenType::Number(0.5),
+                TokenType::EOF
+            ],
+            token_types(".5.5")
+        );
+        assert_eq!(
+            vec![
+                TokenType::Identifier("a".to_string()),
+                TokenType::Dot,
+                TokenType::Identifier("b".to_string()),
+                TokenType::EOF
+            ],
+            token_types("a.b")
+        );
+    }
+}
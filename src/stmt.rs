@@ -2,14 +2,108 @@ use std::fmt::Debug;
 
 use crate::{expr::Expr, token::Token};
 
+#[derive(Clone, Debug)]
+pub struct Parameter {
+    pub name: Token,
+    pub default: Option<Expr>,
+    pub rest: bool,
+}
+
 #[derive(Clone, Debug)]
 pub enum Stmt {
     Print(Expr),
     Expression(Expr),
     Var(Token, Option<Expr>),
-    Block(Vec<Stmt>),
+    Block(Vec<Stmt>, usize),
     If(Expr, Box<Stmt>, Option<Box<Stmt>>),
     While(Expr, Box<Stmt>),
-    Function(Token, Vec<Token>, Vec<Stmt>),
+    /// An initializer, a condition (`true` when left out), an increment and
+    /// a body. The increment runs after every iteration, including one cut
+    /// short by `continue`.
+    For(Option<Box<Stmt>>, Expr, Option<Expr>, Box<Stmt>),
+    Function(Token, Vec<Parameter>, Vec<Stmt>),
     Return(Token, Option<Expr>),
+    /// Leaves the innermost enclosing loop. The parser only accepts it
+    /// inside one.
+    Break(Token),
+    /// Skips to the next iteration of the innermost enclosing loop.
+    Continue(Token),
+    /// A class name, its superclass (always an `Expr::Var`) and its
+    /// methods, each a `Stmt::Function`.
+    Class(Token, Option<Expr>, Vec<Stmt>),
+}
+
+/// One method per kind of statement, called by `Stmt::accept` with the
+/// statement's parts.
+pub trait StmtVisitor<T> {
+    fn visit_print_stmt(&mut self, expr: &Expr) -> T;
+    fn visit_expression_stmt(&mut self, expr: &Expr) -> T;
+    fn visit_var_stmt(&mut self, name: &Token, initializer: Option<&Expr>) -> T;
+    fn visit_block_stmt(&mut self, statements: &[Stmt], line: usize) -> T;
+    fn visit_if_stmt(
+        &mut self,
+        condition: &Expr,
+        then_branch: &Stmt,
+        else_branch: Option<&Stmt>,
+    ) -> T;
+    fn visit_while_stmt(&mut self, condition: &Expr, body: &Stmt) -> T;
+    fn visit_for_stmt(
+        &mut self,
+        initializer: Option<&Stmt>,
+        condition: &Expr,
+        increment: Option<&Expr>,
+        body: &Stmt,
+    ) -> T;
+    fn visit_function_stmt(&mut self, name: &Token, params: &[Parameter], body: &[Stmt]) -> T;
+    fn visit_return_stmt(&mut self, keyword: &Token, value: Option<&Expr>) -> T;
+    fn visit_break_stmt(&mut self, keyword: &Token) -> T;
+    fn visit_continue_stmt(&mut self, keyword: &Token) -> T;
+    fn visit_class_stmt(&mut self, name: &Token, superclass: Option<&Expr>, methods: &[Stmt]) -> T;
+}
+
+impl Stmt {
+    pub fn accept<T>(&self, visitor: &mut impl StmtVisitor<T>) -> T {
+        match self {
+            Self::Print(expr) => visitor.visit_print_stmt(expr),
+            Self::Expression(expr) => visitor.visit_expression_stmt(expr),
+            Self::Var(name, initializer) => visitor.visit_var_stmt(name, initializer.as_ref()),
+            Self::Block(statements, line) => visitor.visit_block_stmt(statements, *line),
+            Self::If(condition, then_branch, else_branch) => {
+                visitor.visit_if_stmt(condition, then_branch, else_branch.as_deref())
+            }
+            Self::While(condition, body) => visitor.visit_while_stmt(condition, body),
+            Self::For(initializer, condition, increment, body) => {
+                visitor.visit_for_stmt(initializer.as_deref(), condition, increment.as_ref(), body)
+            }
+            Self::Function(name, params, body) => visitor.visit_function_stmt(name, params, body),
+            Self::Return(keyword, value) => visitor.visit_return_stmt(keyword, value.as_ref()),
+            Self::Break(keyword) => visitor.visit_break_stmt(keyword),
+            Self::Continue(keyword) => visitor.visit_continue_stmt(keyword),
+            Self::Class(name, superclass, methods) => {
+                visitor.visit_class_stmt(name, superclass.as_ref(), methods)
+            }
+        }
+    }
+
+    /// Whether the statement binds a name in the scope it appears in.
+    pub fn is_declaration(&self) -> bool {
+        matches!(self, Self::Var(..) | Self::Function(..) | Self::Class(..))
+    }
+
+    /// The line the statement starts on.
+    pub fn line(&self) -> usize {
+        match self {
+            Self::Print(expr) | Self::Expression(expr) => expr.line(),
+            Self::If(condition, _, _) | Self::While(condition, _) => condition.line(),
+            Self::For(Some(initializer), _, _, _) => initializer.line(),
+            Self::For(None, condition, _, _) => condition.line(),
+            Self::Block(_, line) => *line,
+            Self::Var(token, _)
+            | Self::Function(token, _, _)
+            | Self::Return(token, _)
+            | Self::Break(token)
+            | Self::Continue(token)
+            | Self::Class(token, _, _) => token.line,
+        }
+    }
 }
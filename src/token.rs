@@ -1,6 +1,7 @@
 use core::fmt;
 use std::{fmt::Display, hash::Hash, hash::Hasher};
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Clone, PartialEq)]
 pub enum TokenType {
     // Single-character tokens.
@@ -8,6 +9,8 @@ pub enum TokenType {
     RightParen,
     LeftBrace,
     RightBrace,
+    Colon,
+    Question,
     Comma,
     Dot,
     Minus,
@@ -15,8 +18,10 @@ pub enum TokenType {
     Semicolon,
     Slash,
     Star,
+    Percent,
 
     // One or two character tokens.
+    StarStar,
     Bang,
     BangEqual,
     Equal,
@@ -25,15 +30,23 @@ pub enum TokenType {
     GreaterEqual,
     Less,
     LessEqual,
+    LessLess,
+    GreaterGreater,
+
+    // Three character tokens.
+    Ellipsis,
 
     // Literals.
     Identifier(String),
     String(String),
+    Interpolation(String),
     Number(f64),
 
     // Keywords.
     And,
+    Break,
     Class,
+    Continue,
     Else,
     False,
     Fun,
@@ -60,6 +73,9 @@ pub struct Token {
     pub lexeme: String,
     pub line: usize,
     pub offset: usize,
+    /// Which scanned source the token came from, so tokens at the same
+    /// position in different programs are told apart. `Token::new` gives 0.
+    pub source_id: usize,
 }
 
 impl Hash for Token {
@@ -81,13 +97,22 @@ impl Token {
             lexeme,
             line,
             offset,
+            source_id: 0,
         }
     }
 
+    /// Whether the tokens read the same, wherever they appear. `==` also
+    /// compares positions.
+    pub fn same_kind(&self, other: &Token) -> bool {
+        self.token_type == other.token_type && self.lexeme == other.lexeme
+    }
+
     pub fn match_keyword(lexeme: &str) -> TokenType {
         match lexeme {
             "and" => TokenType::And,
+            "break" => TokenType::Break,
             "class" => TokenType::Class,
+            "continue" => TokenType::Continue,
             "else" => TokenType::Else,
             "false" => TokenType::False,
             "for" => TokenType::For,
@@ -106,3 +131,34 @@ impl Token {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_kind_ignores_position() {
+        let first = Token::new(
+            TokenType::Identifier("x".to_string()),
+            "x".to_string(),
+            1,
+            0,
+        );
+        let second = Token::new(
+            TokenType::Identifier("x".to_string()),
+            "x".to_string(),
+            4,
+            20,
+        );
+        assert!(first.same_kind(&second));
+        assert_ne!(first, second);
+
+        let other = Token::new(
+            TokenType::Identifier("y".to_string()),
+            "y".to_string(),
+            1,
+            0,
+        );
+        assert!(!first.same_kind(&other));
+    }
+}
@@ -0,0 +1,182 @@
+use std::{
+    io::Write,
+    process::{Command, Output, Stdio},
+};
+
+fn lox(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_lox"))
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn test_warnings_are_not_fatal_by_default() {
+    let output = lox(&["tests/fixtures/unused_variable.lox"]);
+    assert!(output.status.success());
+    assert_eq!("hello\n", stdout(&output));
+    assert!(stderr(&output).contains("Local variable is never read."));
+}
+
+#[test]
+fn test_strict_mode_fails_on_warnings() {
+    for flags in [&["--strict"][..], &["-W", "error"][..]] {
+        let mut args = flags.to_vec();
+        args.push("tests/fixtures/unused_variable.lox");
+        let output = lox(&args);
+        assert_eq!(Some(65), output.status.code());
+        assert!(!stdout(&output).contains("hello"));
+    }
+}
+
+#[test]
+fn test_strict_mode_accepts_empty_file() {
+    let output = lox(&["--strict", "tests/fixtures/empty.lox"]);
+    assert!(output.status.success());
+    assert_eq!("", stdout(&output));
+    assert_eq!("", stderr(&output));
+}
+
+#[test]
+fn test_trace_logs_statements_to_stderr() {
+    let output = lox(&["--trace", "tests/fixtures/trace.lox"]);
+    assert!(output.status.success());
+    assert_eq!("2\n", stdout(&output));
+    let trace = stderr(&output);
+    let lines: Vec<_> = trace
+        .lines()
+        .map(|line| line.split('(').next().unwrap())
+        .collect();
+    assert_eq!(
+        vec![
+            "[line 1] Var",
+            "[line 2] While",
+            "[line 2] Expression",
+            "[line 2] Expression",
+            "[line 3] Print",
+        ],
+        lines
+    );
+}
+
+#[test]
+fn test_breakpoint_inspects_locals() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lox"))
+        .args(["--debug", "tests/fixtures/breakpoint.lox"])
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"secret * 2\ncontinue\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert_eq!("debug> => 84\ndebug> done\n", stdout(&output));
+}
+
+#[test]
+fn test_breakpoint_is_a_no_op_without_debug() {
+    let output = lox(&["tests/fixtures/breakpoint.lox"]);
+    assert_eq!("done\n", stdout(&output));
+}
+
+#[test]
+fn test_script_arguments() {
+    let output = lox(&["tests/fixtures/args.lox", "one", "--two"]);
+    assert!(output.status.success());
+    assert_eq!("[\"one\",\"--two\"]\n", stdout(&output));
+
+    let output = lox(&["tests/fixtures/args.lox", "input.lox", "-i"]);
+    assert!(output.status.success());
+    assert_eq!("[\"input.lox\",\"-i\"]\n", stdout(&output));
+
+    let output = lox(&["tests/fixtures/args.lox", "--", "--", "x.lox"]);
+    assert_eq!("[\"--\",\"x.lox\"]\n", stdout(&output));
+}
+
+#[test]
+fn test_version() {
+    let output = lox(&["--version"]);
+    assert!(output.status.success());
+    let version = stdout(&output);
+    assert!(version.starts_with("lox "));
+    assert!(!version["lox ".len()..].trim().is_empty());
+}
+
+#[test]
+fn test_features() {
+    let output = lox(&["--features"]);
+    assert!(output.status.success());
+    assert!(stdout(&output).lines().any(|line| line == "closures: yes"));
+}
+
+#[test]
+fn test_interactive_after_file_keeps_script_globals() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lox"))
+        .args(["-i", "tests/fixtures/interactive.lox"])
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"square(7)\nloaded\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert_eq!("script done\n> => 49\n> => yes\n> ", stdout(&output));
+}
+
+#[test]
+fn test_custom_repl_prompt_and_result_prefix() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lox"))
+        .args(["--prompt", "lox> ", "--result-prefix", "= "])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"1 + 2\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert_eq!("lox> = 3\nlox> ", stdout(&output));
+}
+
+#[test]
+fn test_scripts_share_one_interpreter() {
+    let output = lox(&[
+        "--load",
+        "tests/fixtures/prelude.lox",
+        "tests/fixtures/uses_prelude.lox",
+        "extra",
+    ]);
+    assert!(output.status.success());
+    assert_eq!("Hello, prelude!\n[\"extra\"]\n", stdout(&output));
+
+    let output = lox(&[
+        "--load",
+        "tests/fixtures/runtime_error.lox",
+        "--load",
+        "tests/fixtures/prelude.lox",
+        "tests/fixtures/uses_prelude.lox",
+    ]);
+    assert_eq!(Some(70), output.status.code());
+    assert!(stdout(&output).starts_with("before\n"));
+    assert!(!stdout(&output).contains("Hello"));
+}
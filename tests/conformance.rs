@@ -0,0 +1,148 @@
+//! Runs the scripts in `tests/lox`, which use the reference test suite's
+//! annotations: every `// expect: <text>` comment is a line the script must
+//! print, in order, and `// expect runtime error: <message>` marks the line
+//! where the script must stop with that error. Scripts listed in
+//! `tests/lox/known_failures.txt` are expected to fail.
+
+#![allow(clippy::result_large_err)]
+
+mod common;
+
+use std::{collections::HashMap, fs, path::Path};
+
+use common::SharedBuffer;
+
+use lox::{
+    errors::LoxError, interpreter::Interpreter, parser::Parser, resolver::Resolver,
+    scanner::Scanner,
+};
+
+/// What a script's annotations say it should do.
+#[derive(Debug, Default, PartialEq)]
+struct Outcome {
+    printed: Vec<String>,
+    /// The line and message of an `// expect runtime error: <message>`.
+    runtime_error: Option<(usize, String)>,
+}
+
+fn expectations(source: &str) -> Outcome {
+    const OUTPUT: &str = "// expect: ";
+    const RUNTIME_ERROR: &str = "// expect runtime error: ";
+    let mut expected = Outcome::default();
+    for (index, line) in source.lines().enumerate() {
+        if let Some(at) = line.find(OUTPUT) {
+            expected.printed.push(line[at + OUTPUT.len()..].to_string());
+        } else if let Some(at) = line.find(RUNTIME_ERROR) {
+            let message = line[at + RUNTIME_ERROR.len()..].to_string();
+            expected.runtime_error = Some((index + 1, message));
+        }
+    }
+    expected
+}
+
+/// Runs a script, returning what it printed and the runtime error it
+/// stopped with, if any. Compile errors are returned as `Err`.
+fn run(source: &str) -> Result<(Vec<String>, Option<LoxError>), String> {
+    let tokens = Scanner::new(source.to_string())
+        .scan_tokens()
+        .map_err(|errors| errors[0].to_string())?;
+    let statements = Parser::new(tokens)
+        .parse()
+        .map_err(|errors| errors[0].to_string())?;
+    let locals = Resolver::new()
+        .resolve_statements(&statements)
+        .map_err(|errors| errors[0].to_string())?;
+
+    let output = SharedBuffer::default();
+    let mut interpreter = Interpreter::with_output(output.clone());
+    interpreter.resolve(locals);
+    let error = interpreter.interpret(&statements).err();
+
+    let printed = output.contents();
+    Ok((printed.lines().map(str::to_string).collect(), error))
+}
+
+/// Checks a script against its annotations, describing the first way it
+/// falls short.
+fn check(source: &str) -> Result<(), String> {
+    let expected = expectations(source);
+    let (printed, error) = run(source)?;
+    if expected.printed != printed {
+        return Err(format!(
+            "expected output {:?}, got {:?}",
+            expected.printed, printed
+        ));
+    }
+
+    match (&expected.runtime_error, error) {
+        (None, None) => Ok(()),
+        (Some((line, message)), Some(error)) => {
+            let shown = error.to_string();
+            if !shown.contains(message.as_str()) {
+                return Err(format!("expected {:?} in {:?}", message, shown));
+            }
+            if *line != error.line {
+                return Err(format!(
+                    "expected the error on line {}, got line {}",
+                    line, error.line
+                ));
+            }
+            Ok(())
+        }
+        (expected, actual) => Err(format!(
+            "expected runtime error {:?}, got {:?}",
+            expected,
+            actual.map(|error| error.to_string())
+        )),
+    }
+}
+
+/// The scripts listed in `known_failures.txt`, by file name, with the
+/// reason each one fails.
+fn known_failures(dir: &Path) -> HashMap<String, String> {
+    let manifest = fs::read_to_string(dir.join("known_failures.txt")).unwrap();
+    manifest
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let (name, reason) = line
+                .split_once(':')
+                .unwrap_or_else(|| panic!("known_failures.txt: no reason in {:?}", line));
+            (name.trim().to_string(), reason.trim().to_string())
+        })
+        .collect()
+}
+
+#[test]
+fn test_conformance_scripts() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/lox");
+    let mut known_failures = known_failures(&dir);
+    let mut paths: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "lox"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty());
+
+    for path in paths {
+        let source = fs::read_to_string(&path).unwrap();
+        let name = path.file_name().unwrap().to_str().unwrap();
+        match (check(&source), known_failures.remove(name)) {
+            (Ok(()), None) => (),
+            (Err(error), None) => panic!("{}: {}", path.display(), error),
+            (Ok(()), Some(reason)) => panic!(
+                "{} passes now, despite {:?}; take it off known_failures.txt",
+                path.display(),
+                reason
+            ),
+            (Err(_), Some(_)) => (),
+        }
+    }
+    assert!(
+        known_failures.is_empty(),
+        "known_failures.txt lists missing scripts: {:?}",
+        known_failures.keys().collect::<Vec<_>>()
+    );
+}
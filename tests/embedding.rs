@@ -0,0 +1,177 @@
+#![allow(clippy::result_large_err)]
+
+mod common;
+
+use std::{cell::Cell, rc::Rc};
+
+use common::SharedBuffer;
+use lox::{
+    errors::{DetailedErrorType, LoxErrorType},
+    interpreter::{DisplayFormat, EvaluationResult, Interpreter},
+    literal::Literal,
+    parser::Parser,
+    resolver::Resolution,
+    scanner::Scanner,
+};
+
+fn run(interpreter: &mut Interpreter, source: &str) -> EvaluationResult {
+    let tokens = Scanner::new(source.to_string()).scan_tokens().unwrap();
+    let statements = Parser::new(tokens).parse().unwrap();
+    interpreter.interpret(&statements)
+}
+
+#[test]
+fn test_custom_native_closure() {
+    let calls = Rc::new(Cell::new(0));
+    let counter = Rc::clone(&calls);
+    let factor = 3.0;
+
+    let mut interpreter = Interpreter::new();
+    interpreter.define_native_fn("scale", 1, move |_, args| {
+        counter.set(counter.get() + 1);
+        match &args[0] {
+            Literal::Number(n) => Ok(Literal::Number(n * factor)),
+            _ => Err(DetailedErrorType::InvalidArgument(
+                "scale expects a number.".to_string(),
+            )),
+        }
+    });
+
+    assert_eq!(
+        Literal::Number(21.0),
+        run(&mut interpreter, "scale(2) + scale(5);").unwrap()
+    );
+    assert_eq!(2, calls.get());
+
+    let err = run(&mut interpreter, "scale(\"x\");").unwrap_err();
+    assert_eq!(
+        LoxErrorType::RuntimeError(DetailedErrorType::InvalidArgument(
+            "scale expects a number.".to_string()
+        )),
+        err.kind
+    );
+}
+
+#[test]
+fn test_compile_once_run_twice() {
+    let program =
+        lox::compile("var total = 0; for (var i = 1; i <= 4; i = i + 1) total = total + i; total;")
+            .unwrap();
+    for _ in 0..2 {
+        let mut interpreter = Interpreter::new();
+        assert_eq!(
+            Literal::Number(10.0),
+            interpreter.run_compiled(&program).unwrap()
+        );
+    }
+
+    let errors = lox::compile("var s = \"open;").unwrap_err();
+    assert_eq!(1, errors.len());
+    assert_eq!(
+        LoxErrorType::SyntaxError("Unterminated string literal".to_string()),
+        errors[0].kind
+    );
+}
+
+#[test]
+fn test_resolved_program_runs_repeatedly() {
+    let program = lox::compile_resolved(
+        "fun sum(n) { var total = 0; for (var i = 1; i <= n; i = i + 1) total = total + i; return total; } sum(4);",
+    )
+    .unwrap();
+    assert!(!program.locals.is_empty());
+    let first = Interpreter::new().run_compiled(&program).unwrap();
+    let second = Interpreter::new().run_compiled(&program).unwrap();
+    assert_eq!(Literal::Number(10.0), first);
+    assert_eq!(first, second);
+
+    let closure = lox::compile_resolved(
+        "fun counter() { var n = 0; fun inc() { n = n + 1; return n; } return inc; } var c = counter(); c(); c();",
+    )
+    .unwrap();
+    assert_eq!(
+        Literal::Number(2.0),
+        Interpreter::new().run_compiled(&closure).unwrap()
+    );
+
+    // The resolution shipped with the program is what runs: pointing the
+    // shadowing read one scope further out makes it see the outer `x`.
+    let shadowing =
+        lox::compile_resolved("var seen; { var x = 1; { var x = 2; seen = x; } } seen;").unwrap();
+    assert_eq!(
+        Literal::Number(2.0),
+        Interpreter::new().run_compiled(&shadowing).unwrap()
+    );
+    let moved = lox::Program {
+        statements: shadowing.statements,
+        locals: Rc::new(
+            shadowing
+                .locals
+                .iter()
+                .map(|(token, resolution)| match resolution {
+                    Resolution::Local(distance) => (token.clone(), Resolution::Local(distance + 1)),
+                    Resolution::Global => (token.clone(), Resolution::Global),
+                })
+                .collect(),
+        ),
+    };
+    assert_eq!(
+        Literal::Number(1.0),
+        Interpreter::new().run_compiled(&moved).unwrap()
+    );
+
+    let errors = lox::compile_resolved("{ var a = a; }").err().unwrap();
+    assert_eq!(
+        "[line 1] Error at 'a': Can't read local variable in its own initializer.",
+        errors[0].to_string()
+    );
+}
+
+#[test]
+fn test_print_debug_quotes_and_escapes() {
+    let output = SharedBuffer::default();
+    let mut interpreter = Interpreter::with_output(output.clone());
+    run(
+        &mut interpreter,
+        "printDebug(\"a\\tb \\\"c\\\" \\u{1}\"); printDebug(1.5); print \"a\\tb\";",
+    )
+    .unwrap();
+    assert_eq!("\"a\\tb \\\"c\\\" \\u{1}\"\n1.5\na\tb\n", output.contents());
+}
+
+#[test]
+fn test_display_format_for_booleans_and_nil() {
+    let source = "print nil; print true; print false; print 1; print \"${nil}\";";
+
+    let output = SharedBuffer::default();
+    let mut interpreter = Interpreter::with_output(output.clone());
+    run(&mut interpreter, source).unwrap();
+    assert_eq!("nil\ntrue\nfalse\n1\nnil\n", output.contents());
+
+    let output = SharedBuffer::default();
+    let mut interpreter = Interpreter::with_output(output.clone());
+    interpreter.display = DisplayFormat {
+        true_text: "True".to_string(),
+        false_text: "False".to_string(),
+        nil_text: "null".to_string(),
+    };
+    run(&mut interpreter, source).unwrap();
+    assert_eq!("null\nTrue\nFalse\n1\nnil\n", output.contents());
+}
+
+#[test]
+fn test_breakpoint_reads_scripted_input() {
+    let output = SharedBuffer::default();
+    let mut interpreter = Interpreter::with_output(output.clone());
+    interpreter.debug = true;
+    let mut input = vec!["secret * 2\n", "secret = ;\n", "continue\n"].into_iter();
+    interpreter.read_line = Box::new(move || input.next().map(str::to_string));
+
+    let program =
+        lox::compile("fun f() { var secret = 42; breakpoint(); print secret; } f();").unwrap();
+    interpreter.run_compiled(&program).unwrap();
+    assert_eq!(
+        "debug> => 84\ndebug> [line 1] Error at ';': Expected expression\ndebug> 42\n",
+        output.contents()
+    );
+}